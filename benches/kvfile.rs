@@ -0,0 +1,74 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use soapdav::adapter::storage::*;
+
+const FILE_AMOUNT: usize = 100_000;
+
+// 模拟一个有很多章节的库，每个系列100话
+fn prepare() -> MemFileKVFileStorage {
+    let storage = MemFileKVFileStorage::new();
+    for i in 0..FILE_AMOUNT {
+        let params = AddFileParams {
+            label: vec![
+                KV::new(String::from("title"), format!("chapter-{}", i)),
+                KV::new(String::from("series"), format!("series-{}", i / 100)),
+                KV::new(String::from("volume"), (i % 100).to_string()),
+            ],
+        };
+        storage.add_file(&params).unwrap();
+    }
+    storage
+}
+
+fn series(name: &str) -> Selector {
+    Selector::new(String::from("series"), vec![String::from(name)])
+}
+
+fn bench_list_file(c: &mut Criterion) {
+    let storage = prepare();
+    let indexed = ListFileParams {
+        ids: vec![],
+        selectors: vec![series("series-42")],
+        filter: None,
+    };
+    c.bench_function("list_file indexed", |b| {
+        b.iter(|| storage.list_file(black_box(&indexed)).unwrap())
+    });
+    // 否定的条件用不了索引，结果相同，用来和全量扫描对比
+    let scan = ListFileParams {
+        ids: vec![],
+        selectors: vec![],
+        filter: Some(SelectorExpr::Not(Box::new(SelectorExpr::Not(Box::new(
+            SelectorExpr::Selector(series("series-42")),
+        ))))),
+    };
+    c.bench_function("list_file scan", |b| {
+        b.iter(|| storage.list_file(black_box(&scan)).unwrap())
+    });
+    let range = ListFileParams {
+        ids: vec![],
+        selectors: vec![
+            series("series-42"),
+            Selector::with_range(String::from("volume"), Some(String::from("10")), None),
+        ],
+        filter: None,
+    };
+    c.bench_function("list_file range", |b| {
+        b.iter(|| storage.list_file(black_box(&range)).unwrap())
+    });
+}
+
+fn bench_list_selector(c: &mut Criterion) {
+    let storage = prepare();
+    let params = ListSelectorParams {
+        key: vec![String::from("series")],
+        exclude_key: vec![],
+        value: vec![],
+    };
+    c.bench_function("list_selector", |b| {
+        b.iter(|| storage.list_selector(black_box(&params)).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_list_file, bench_list_selector);
+criterion_main!(benches);
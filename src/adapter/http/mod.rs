@@ -0,0 +1,4 @@
+mod response;
+mod server;
+
+pub use server::*;
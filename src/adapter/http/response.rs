@@ -0,0 +1,78 @@
+use http::{HeaderValue, Response, StatusCode};
+use hyper::body;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use webdav_handler::body::Body;
+
+use crate::FilesystemError;
+
+// 管理接口出错时返回的内容
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    code: String,
+    message: String,
+}
+
+pub fn json_response<T: Serialize>(status: StatusCode, value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(body) => with_json_body(status, body),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, "backend", e.to_string()),
+    }
+}
+
+pub fn error_response(status: StatusCode, code: &str, message: String) -> Response<Body> {
+    let body = ErrorResponse {
+        code: code.to_string(),
+        message,
+    };
+    with_json_body(status, serde_json::to_string(&body).unwrap_or_default())
+}
+
+pub fn invalid_argument(message: String) -> Response<Body> {
+    error_response(StatusCode::BAD_REQUEST, "invalid_argument", message)
+}
+
+fn with_json_body(status: StatusCode, body: String) -> Response<Body> {
+    let mut resp = Response::new(Body::from(body));
+    *resp.status_mut() = status;
+    resp.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    resp
+}
+
+// 成功时使用 status 作为状态码，失败时根据错误类型决定
+pub fn to_response<T: Serialize>(
+    status: StatusCode,
+    result: Result<T, FilesystemError>,
+) -> Response<Body> {
+    match result {
+        Ok(r) => json_response(status, &r),
+        Err(e) => {
+            let status = match e {
+                FilesystemError::NotFound => StatusCode::NOT_FOUND,
+                FilesystemError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+                FilesystemError::Conflict(_) | FilesystemError::DuplicateTitle(_) => {
+                    StatusCode::CONFLICT
+                }
+                FilesystemError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            error_response(status, e.code(), e.to_string())
+        }
+    }
+}
+
+// 请求体读取失败或者不是合法的 JSON 时直接返回 400
+pub async fn read_params<T: DeserializeOwned>(
+    req: hyper::Request<hyper::Body>,
+) -> Result<T, Response<Body>> {
+    let whole_body = match body::to_bytes(req.into_body()).await {
+        Ok(v) => v,
+        Err(e) => return Err(invalid_argument(e.to_string())),
+    };
+    match serde_json::from_slice(&whole_body) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(invalid_argument(e.to_string())),
+    }
+}
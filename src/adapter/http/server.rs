@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use http::{Method, Response, StatusCode};
+use log::info;
+use percent_encoding::percent_decode_str;
+use webdav_handler::body::Body;
+use webdav_handler::{fakels, DavHandler};
+
+use super::response::*;
+use crate::adapter::storage::{parse_query, Selector};
+use crate::*;
+
+// 带版本号的管理接口
+const MANAGE_PREFIX: &str = "/manage/v1/";
+
+// WebDAV 请求交给 DavHandler 处理，/manage 下的请求由管理接口处理
+#[derive(Clone)]
+pub struct Server {
+    dh: DavHandler,
+    fs: SimpleFileSystem,
+}
+
+impl Server {
+    pub fn new(fs: SimpleFileSystem) -> Self {
+        let config = DavHandler::builder()
+            .filesystem(Box::new(fs.clone()))
+            .locksystem(fakels::FakeLs::new())
+            .autoindex(true, None);
+        Server {
+            dh: config.build_handler(),
+            fs,
+        }
+    }
+
+    pub async fn handle(
+        &self,
+        req: hyper::Request<hyper::Body>,
+    ) -> Result<hyper::Response<Body>, Infallible> {
+        let path = req.uri().path().to_string();
+        if let Some(route) = path.strip_prefix(MANAGE_PREFIX) {
+            return Ok(self.handle_manage(req, route).await);
+        }
+        // 旧版的管理接口，只保留已有的几个
+        let resp = match path.as_str() {
+            "/manage/add_file" => self.add_file(req).await,
+            "/manage/define_collection" => self.define_collection(req).await,
+            "/manage/remove_collection" => self.remove_collection(req).await,
+            "/manage/define_selector" => self.define_selector(req).await,
+            _ => {
+                info!("receive dav request, method={}, path={}", req.method(), path);
+                self.dh.handle(req).await
+            }
+        };
+        Ok(resp)
+    }
+
+    async fn handle_manage(&self, req: hyper::Request<hyper::Body>, route: &str) -> Response<Body> {
+        let segments: Vec<String> = route
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+        let method = req.method().clone();
+        info!("receive manage request, method={}, route={}", method, route);
+        match (&method, segments.as_slice()) {
+            (&Method::GET, ["collections"]) => self.list_collection(req),
+            (&Method::POST, ["collections"]) => self.define_collection(req).await,
+            (&Method::DELETE, ["collections", name]) => to_response(
+                StatusCode::OK,
+                self.fs.remove_collection(&RemoveCollectionParams {
+                    name: vec![name.to_string()],
+                }),
+            ),
+            (&Method::PATCH, ["collections", name]) => {
+                self.update_collection(req, name.to_string()).await
+            }
+            (&Method::GET, ["selectors"]) => self.list_selector(req),
+            (&Method::POST, ["selectors"]) => self.define_selector(req).await,
+            (&Method::GET, ["selectors", key, "aliases"]) => to_response(
+                StatusCode::OK,
+                self.fs.list_alias(&ListAliasParams {
+                    key: vec![key.to_string()],
+                }),
+            ),
+            (&Method::POST, ["selectors", key, "aliases"]) => {
+                self.define_alias(req, key.to_string()).await
+            }
+            (&Method::DELETE, ["selectors", key, "aliases"]) => {
+                let params = RemoveAliasParams {
+                    key: key.to_string(),
+                    aliases: query_values(&req, "alias"),
+                };
+                to_response(StatusCode::OK, self.fs.remove_alias(&params))
+            }
+            (&Method::POST, ["selectors", key, "merge"]) => {
+                self.merge_value(req, key.to_string()).await
+            }
+            (&Method::PATCH, ["selectors", key]) => self.rename_selector(req, key.to_string()).await,
+            (&Method::DELETE, ["selectors", key]) => {
+                let params = RemoveSelectorParams {
+                    key: key.to_string(),
+                    cascade: query_values(&req, "cascade").iter().any(|v| v == "true"),
+                };
+                to_response(StatusCode::OK, self.fs.remove_selector(&params))
+            }
+            (&Method::GET, ["files"]) => self.list_file(req),
+            (&Method::GET, ["search"]) => self.search_file(req),
+            (&Method::POST, ["files"]) => self.add_file(req).await,
+            (&Method::GET, ["files", id]) => match parse_id(id) {
+                Ok(id) => self.get_file(id),
+                Err(resp) => resp,
+            },
+            (&Method::DELETE, ["files", id]) => match parse_id(id) {
+                Ok(id) => to_response(
+                    StatusCode::OK,
+                    self.fs.remove_file(&RemoveFileParams { ids: vec![id] }),
+                ),
+                Err(resp) => resp,
+            },
+            (&Method::PUT, ["files", id, "labels"]) => match parse_id(id) {
+                Ok(id) => self.set_label(req, id).await,
+                Err(resp) => resp,
+            },
+            (&Method::POST, ["trash", "restore"]) => self.restore_file(req).await,
+            (&Method::POST, ["trash", "purge"]) => self.purge_file(req).await,
+            (_, ["collections"])
+            | (_, ["collections", _])
+            | (_, ["selectors"])
+            | (_, ["selectors", _])
+            | (_, ["selectors", _, "aliases"])
+            | (_, ["selectors", _, "merge"])
+            | (_, ["files"])
+            | (_, ["files", _])
+            | (_, ["files", _, "labels"])
+            | (_, ["search"])
+            | (_, ["trash", "restore"])
+            | (_, ["trash", "purge"]) => error_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "method_not_allowed",
+                format!("{} is not allowed on {}", method, route),
+            ),
+            _ => error_response(
+                StatusCode::NOT_FOUND,
+                "not_found",
+                format!("unknown route {}", route),
+            ),
+        }
+    }
+
+    // ?name=a&name=b，不带参数时返回所有的筛选器组
+    fn list_collection(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        let params = ListCollectionParams {
+            names: query_values(&req, "name"),
+        };
+        to_response(StatusCode::OK, self.fs.list_collection(&params))
+    }
+
+    // ?key=a&key=b
+    fn list_selector(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        let params = ListSelectorParams {
+            key: query_values(&req, "key"),
+            exclude_key: vec![],
+            value: vec![],
+        };
+        to_response(StatusCode::OK, self.fs.list_selector(&params))
+    }
+
+    // ?id=1&id=2&author=oda，除了 id 以外的参数都作为筛选条件，同一个key的多个值之间是或的关系
+    fn list_file(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        let mut ids = vec![];
+        let mut labels: HashMap<String, Vec<String>> = HashMap::new();
+        for (k, v) in query_pairs(&req) {
+            if k == "id" {
+                match parse_id(&v) {
+                    Ok(id) => ids.push(id),
+                    Err(resp) => return resp,
+                }
+            } else {
+                labels.entry(k).or_default().push(v);
+            }
+        }
+        let params = ListFileParams {
+            ids,
+            selectors: labels.into_iter().map(|(k, vs)| Selector::new(k, vs)).collect(),
+            filter: None,
+        };
+        to_response(StatusCode::OK, self.fs.list_file(&params))
+    }
+
+    // ?q=author:"Oda" AND volume>=3，语法见 storage::query
+    fn search_file(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        let query = query_values(&req, "q").join(" ");
+        let filter = match parse_query(&query) {
+            Ok(v) => v,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, "invalid_query", e.to_string()),
+        };
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![],
+            filter: Some(filter),
+        };
+        to_response(StatusCode::OK, self.fs.list_file(&params))
+    }
+
+    fn get_file(&self, id: u64) -> Response<Body> {
+        let result = self
+            .fs
+            .list_file(&ListFileParams {
+                ids: vec![id],
+                selectors: vec![],
+                filter: None,
+            })
+            .and_then(|r| r.files.into_iter().next().ok_or(FilesystemError::NotFound));
+        to_response(StatusCode::OK, result)
+    }
+
+    async fn set_label(&self, req: hyper::Request<hyper::Body>, id: u64) -> Response<Body> {
+        match read_params::<SetLabelParams>(req).await {
+            Ok(mut params) => {
+                params.id = id;
+                to_response(StatusCode::OK, self.fs.set_label(&params))
+            }
+            Err(resp) => resp,
+        }
+    }
+
+    async fn update_collection(&self, req: hyper::Request<hyper::Body>, name: String) -> Response<Body> {
+        match read_params::<UpdateCollectionParams>(req).await {
+            Ok(mut params) => {
+                params.name = name;
+                to_response(StatusCode::OK, self.fs.update_collection(&params))
+            }
+            Err(resp) => resp,
+        }
+    }
+
+    async fn rename_selector(&self, req: hyper::Request<hyper::Body>, key: String) -> Response<Body> {
+        match read_params::<RenameSelectorParams>(req).await {
+            Ok(mut params) => {
+                params.key = key;
+                to_response(StatusCode::OK, self.fs.rename_selector(&params))
+            }
+            Err(resp) => resp,
+        }
+    }
+
+    async fn define_alias(&self, req: hyper::Request<hyper::Body>, key: String) -> Response<Body> {
+        match read_params::<DefineAliasParams>(req).await {
+            Ok(mut params) => {
+                params.key = key;
+                to_response(StatusCode::CREATED, self.fs.define_alias(&params))
+            }
+            Err(resp) => resp,
+        }
+    }
+
+    async fn merge_value(&self, req: hyper::Request<hyper::Body>, key: String) -> Response<Body> {
+        match read_params::<MergeValueParams>(req).await {
+            Ok(mut params) => {
+                params.key = key;
+                to_response(StatusCode::OK, self.fs.merge_value(&params))
+            }
+            Err(resp) => resp,
+        }
+    }
+
+    async fn add_file(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        match read_params::<AddFileParams>(req).await {
+            Ok(params) => to_response(StatusCode::CREATED, self.fs.add_file(&params)),
+            Err(resp) => resp,
+        }
+    }
+
+    async fn define_collection(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        match read_params::<DefineCollectionParams>(req).await {
+            Ok(params) => to_response(StatusCode::CREATED, self.fs.define_collection(&params)),
+            Err(resp) => resp,
+        }
+    }
+
+    async fn remove_collection(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        match read_params::<RemoveCollectionParams>(req).await {
+            Ok(params) => to_response(StatusCode::OK, self.fs.remove_collection(&params)),
+            Err(resp) => resp,
+        }
+    }
+
+    async fn define_selector(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        match read_params::<DefineSelectorParams>(req).await {
+            Ok(params) => to_response(StatusCode::CREATED, self.fs.define_selector(&params)),
+            Err(resp) => resp,
+        }
+    }
+
+    async fn restore_file(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        match read_params::<RestoreFileParams>(req).await {
+            Ok(params) => to_response(StatusCode::OK, self.fs.restore_file(&params)),
+            Err(resp) => resp,
+        }
+    }
+
+    async fn purge_file(&self, req: hyper::Request<hyper::Body>) -> Response<Body> {
+        match read_params::<PurgeFileParams>(req).await {
+            Ok(params) => to_response(StatusCode::OK, self.fs.purge_file(&params)),
+            Err(resp) => resp,
+        }
+    }
+}
+
+fn query_pairs(req: &hyper::Request<hyper::Body>) -> Vec<(String, String)> {
+    match req.uri().query() {
+        Some(q) => url::form_urlencoded::parse(q.as_bytes())
+            .into_owned()
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn query_values(req: &hyper::Request<hyper::Body>, key: &str) -> Vec<String> {
+    query_pairs(req)
+        .into_iter()
+        .filter(|(k, _)| k == key)
+        .map(|(_, v)| v)
+        .collect()
+}
+
+fn parse_id(value: &str) -> Result<u64, Response<Body>> {
+    value
+        .parse::<u64>()
+        .map_err(|_| invalid_argument(format!("invalid file id: {}", value)))
+}
@@ -0,0 +1,126 @@
+use std::{fmt::Debug, time::SystemTime};
+
+use bytes::Bytes;
+use mockall::automock;
+use thiserror::Error;
+
+// 定义 BlobStorage 错误, 用于处理可能出现的错误情况
+#[derive(Error, Debug)]
+pub enum BlobStorageError {
+    #[error("NotFound")]
+    NotFound,
+    #[error("Backend({0})")]
+    Backend(String),
+}
+
+impl From<std::io::Error> for BlobStorageError {
+    fn from(value: std::io::Error) -> Self {
+        match value.kind() {
+            std::io::ErrorKind::NotFound => BlobStorageError::NotFound,
+            _ => BlobStorageError::Backend(value.to_string()),
+        }
+    }
+}
+
+// BlobStorage trait, 以 KVFile 的 id 为索引保存文件本体
+#[automock]
+pub trait BlobStorage: Send + Sync + Debug {
+    fn stat_blob<'a>(
+        &'a self,
+        params: &'a StatBlobParams,
+    ) -> Result<StatBlobResult, BlobStorageError>;
+
+    fn read_blob<'a>(
+        &'a self,
+        params: &'a ReadBlobParams,
+    ) -> Result<ReadBlobResult, BlobStorageError>;
+
+    // 创建一个临时的文件本体，之后的写入都落在临时文件本体上，commit_blob 之前正式的文件本体保持不变
+    fn create_blob<'a>(
+        &'a self,
+        params: &'a CreateBlobParams,
+    ) -> Result<CreateBlobResult, BlobStorageError>;
+
+    fn write_blob<'a>(
+        &'a self,
+        params: &'a WriteBlobParams,
+    ) -> Result<WriteBlobResult, BlobStorageError>;
+
+    // 临时文件本体落盘之后整个替换掉正式的文件本体
+    fn commit_blob<'a>(
+        &'a self,
+        params: &'a CommitBlobParams,
+    ) -> Result<CommitBlobResult, BlobStorageError>;
+
+    fn remove_blob<'a>(
+        &'a self,
+        params: &'a RemoveBlobParams,
+    ) -> Result<RemoveBlobResult, BlobStorageError>;
+}
+
+// 请求的参数定义
+#[derive(Debug, Clone)]
+pub struct StatBlobParams {
+    pub id: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadBlobParams {
+    pub id: u64,
+    pub offset: u64,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateBlobParams {
+    pub id: u64,
+    // 为 false 时临时文件本体从现有的内容开始，用于追加和局部写入
+    pub truncate: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct WriteBlobParams {
+    pub temp: String,
+    pub offset: u64,
+    pub body: Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitBlobParams {
+    pub id: u64,
+    pub temp: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoveBlobParams {
+    pub id: u64,
+}
+
+// 响应的结果定义
+#[derive(Debug, Clone)]
+pub struct StatBlobResult {
+    pub size: u64,
+    pub modified_time: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadBlobResult {
+    pub body: Bytes,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateBlobResult {
+    pub temp: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WriteBlobResult {
+    pub size: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitBlobResult {}
+
+#[derive(Debug, Clone)]
+pub struct RemoveBlobResult {}
@@ -1,4 +1,4 @@
-use super::{SelectorStorage, Selectors};
+use super::{SelectorExpr, SelectorStorage, Selectors, ValueType};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -8,6 +8,11 @@ use thiserror::Error;
 pub enum KVFileStorageError {
     #[error("NotFound")]
     NotFound,
+    // 参数不合法，比如空的key或者名字
+    #[error("InvalidArgument({0})")]
+    InvalidArgument(String),
+    #[error("Backend({0})")]
+    Backend(String),
 }
 
 // KVFileStorage trait
@@ -31,6 +36,23 @@ pub trait KVFileStorage: SelectorStorage {
         &'a self,
         params: &'a SetLabelParams,
     ) -> Result<SetLabelResult, KVFileStorageError>;
+
+    // 在已有的值之外追加新的值，用于一个文件同时属于多个系列、多个作者的情况
+    fn add_label_value<'a>(
+        &'a self,
+        params: &'a AddLabelValueParams,
+    ) -> Result<AddLabelValueResult, KVFileStorageError>;
+
+    fn remove_label_value<'a>(
+        &'a self,
+        params: &'a RemoveLabelValueParams,
+    ) -> Result<RemoveLabelValueResult, KVFileStorageError>;
+
+    // 先删后加，两边要么都生效要么都不生效，用于移动文件这类需要同时改多个值的操作
+    fn replace_label_value<'a>(
+        &'a self,
+        params: &'a ReplaceLabelValueParams,
+    ) -> Result<ReplaceLabelValueResult, KVFileStorageError>;
 }
 
 // KV 定义
@@ -40,8 +62,26 @@ pub struct KV {
     pub value: String,
 }
 
+// 筛选目录中表示没有设置这个标签的目录名，不能作为标签的值
+pub const UNSET_VALUE: &str = "_unset";
+
+// 文件系统维护的大小和修改时间，存储启动时就声明好类型，可以直接按范围筛选
+pub const BODY_SIZE_KEY: &str = "body_size";
+pub const MODIFIED_TIME_KEY: &str = "modified_time";
+
+pub fn system_value_types() -> HashMap<String, ValueType> {
+    HashMap::from([
+        (String::from(BODY_SIZE_KEY), ValueType::Int),
+        (String::from(MODIFIED_TIME_KEY), ValueType::Date),
+    ])
+}
+
+// 同一个key可以出现多次，表示这个标签有多个值
 pub type KVs = Vec<KV>;
 
+// 按key聚合之后的标签，value按添加的先后顺序排列
+pub type Labels = HashMap<String, Vec<String>>;
+
 impl KV {
     pub fn find_value(kvs: &KVs, key: &String) -> Option<String> {
         for kv in kvs {
@@ -52,6 +92,13 @@ impl KV {
         None
     }
 
+    pub fn find_values(kvs: &KVs, key: &String) -> Vec<String> {
+        kvs.iter()
+            .filter(|kv| kv.key.eq(key))
+            .map(|kv| kv.value.to_string())
+            .collect()
+    }
+
     pub fn find_value_default(kvs: &KVs, key: &String, default_value: String) -> String {
         match KV::find_value(kvs, key) {
             Some(v) => v,
@@ -81,10 +128,44 @@ impl KV {
     pub fn from_hash_map(kvs: HashMap<String, String>) -> KVs {
         kvs.iter().map(KV::from_pair).collect()
     }
+
+    pub fn to_labels(kvs: &KVs) -> Labels {
+        let mut labels = Labels::new();
+        for kv in kvs {
+            let values = labels.entry(kv.key.clone()).or_insert_with(Vec::new);
+            if !values.contains(&kv.value) {
+                values.push(kv.value.clone());
+            }
+        }
+        labels
+    }
+
+    // 标签的key不能为空，值不能是保留的 UNSET_VALUE
+    pub fn validate(kvs: &KVs) -> Result<(), KVFileStorageError> {
+        if kvs.iter().any(|kv| kv.key.is_empty()) {
+            return Err(KVFileStorageError::InvalidArgument(String::from(
+                "label key is empty",
+            )));
+        }
+        match kvs.iter().find(|kv| kv.value == UNSET_VALUE) {
+            Some(kv) => Err(KVFileStorageError::InvalidArgument(format!(
+                "{} is reserved and can not be a value of {}",
+                UNSET_VALUE, kv.key
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn from_labels(labels: &Labels) -> KVs {
+        labels
+            .iter()
+            .flat_map(|(k, vs)| vs.iter().map(move |v| KV::new(k.clone(), v.clone())))
+            .collect()
+    }
 }
 
 // KVFile 的定义
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KVFile {
     pub id: u64,
     pub label: KVs,
@@ -95,6 +176,8 @@ pub struct KVFile {
 pub struct ListFileParams {
     pub ids: Vec<u64>,
     pub selectors: Selectors,
+    // 在selectors之外还要满足的组合条件
+    pub filter: Option<SelectorExpr>,
 }
 
 #[derive(Debug, Clone)]
@@ -102,18 +185,54 @@ pub struct ListFileResult {
     pub files: Vec<KVFile>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SetLabelParams {
     pub id: u64,
     pub label: HashMap<String, String>,
 }
 
+// 用新的值替换掉key原有的所有值
 #[derive(Debug, Clone)]
 pub struct SetLabelResult {
-    pub kvs: HashMap<String, String>,
+    pub kvs: Labels,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddLabelValueParams {
+    pub id: u64,
+    pub label: KVs,
+}
+
+#[derive(Debug, Clone)]
+pub struct AddLabelValueResult {
+    pub kvs: Labels,
+}
+
+// 值被删光之后，key也会一起删掉
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveLabelValueParams {
+    pub id: u64,
+    pub label: KVs,
 }
 
 #[derive(Debug, Clone)]
+pub struct RemoveLabelValueResult {
+    pub kvs: Labels,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaceLabelValueParams {
+    pub id: u64,
+    pub removed: KVs,
+    pub added: KVs,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplaceLabelValueResult {
+    pub kvs: Labels,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoveFileParams {
     pub ids: Vec<u64>,
 }
@@ -123,7 +242,7 @@ pub struct RemoveFileResult {
     pub amount: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddFileParams {
     pub label: Vec<KV>,
 }
@@ -131,5 +250,5 @@ pub struct AddFileParams {
 #[derive(Debug, Clone)]
 pub struct AddFileResult {
     pub id: u64,
-    pub label: HashMap<String, String>,
+    pub label: Labels,
 }
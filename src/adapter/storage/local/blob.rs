@@ -0,0 +1,124 @@
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use bytes::Bytes;
+
+use crate::adapter::storage::*;
+
+const TEMP_SUFFIX: &str = ".tmp";
+
+// 以目录形式保存文件本体，每个 KVFile 对应目录下一个以 id 命名的文件
+#[derive(Debug, Clone)]
+pub struct LocalBlobStorage {
+    root: PathBuf,
+}
+
+impl LocalBlobStorage {
+    pub fn new(root: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&root)?;
+        // 上次没有提交的临时文件本体已经没用了
+        for entry in fs::read_dir(&root)? {
+            let path = entry?.path();
+            if path.to_string_lossy().ends_with(TEMP_SUFFIX) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(LocalBlobStorage { root })
+    }
+
+    fn blob_path(&self, id: u64) -> PathBuf {
+        self.root.join(id.to_string())
+    }
+
+    // 临时文件本体只能是 create_blob 生成的名字，不能指向目录外面
+    fn temp_path(&self, temp: &String) -> Result<PathBuf, BlobStorageError> {
+        if !temp.ends_with(TEMP_SUFFIX) || temp.contains(std::path::is_separator) {
+            return Err(BlobStorageError::NotFound);
+        }
+        Ok(self.root.join(temp))
+    }
+}
+
+impl BlobStorage for LocalBlobStorage {
+    fn stat_blob<'a>(
+        &'a self,
+        params: &'a StatBlobParams,
+    ) -> Result<StatBlobResult, BlobStorageError> {
+        let meta = fs::metadata(self.blob_path(params.id))?;
+        Ok(StatBlobResult {
+            size: meta.len(),
+            modified_time: meta.modified()?,
+        })
+    }
+
+    fn read_blob<'a>(
+        &'a self,
+        params: &'a ReadBlobParams,
+    ) -> Result<ReadBlobResult, BlobStorageError> {
+        let mut file = File::open(self.blob_path(params.id))?;
+        file.seek(SeekFrom::Start(params.offset))?;
+        let mut body = Vec::with_capacity(params.size);
+        file.take(params.size as u64).read_to_end(&mut body)?;
+        Ok(ReadBlobResult {
+            body: Bytes::from(body),
+        })
+    }
+
+    fn create_blob<'a>(
+        &'a self,
+        params: &'a CreateBlobParams,
+    ) -> Result<CreateBlobResult, BlobStorageError> {
+        let temp = format!("{}.{}{}", params.id, uuid::Uuid::new_v4(), TEMP_SUFFIX);
+        let path = self.root.join(&temp);
+        let copied = match params.truncate {
+            true => None,
+            false => match fs::copy(self.blob_path(params.id), &path) {
+                Ok(size) => Some(size),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            },
+        };
+        let size = match copied {
+            Some(size) => size,
+            None => {
+                File::create(&path)?;
+                0
+            }
+        };
+        Ok(CreateBlobResult { temp, size })
+    }
+
+    fn write_blob<'a>(
+        &'a self,
+        params: &'a WriteBlobParams,
+    ) -> Result<WriteBlobResult, BlobStorageError> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .open(self.temp_path(&params.temp)?)?;
+        file.seek(SeekFrom::Start(params.offset))?;
+        file.write_all(&params.body)?;
+        Ok(WriteBlobResult {
+            size: file.metadata()?.len(),
+        })
+    }
+
+    fn commit_blob<'a>(
+        &'a self,
+        params: &'a CommitBlobParams,
+    ) -> Result<CommitBlobResult, BlobStorageError> {
+        let temp = self.temp_path(&params.temp)?;
+        fs::OpenOptions::new().write(true).open(&temp)?.sync_all()?;
+        // 同一个文件系统内的 rename 是原子的，读的一方要么看到旧的内容要么看到新的
+        fs::rename(&temp, self.blob_path(params.id))?;
+        Ok(CommitBlobResult {})
+    }
+
+    fn remove_blob<'a>(
+        &'a self,
+        params: &'a RemoveBlobParams,
+    ) -> Result<RemoveBlobResult, BlobStorageError> {
+        fs::remove_file(self.blob_path(params.id))?;
+        Ok(RemoveBlobResult {})
+    }
+}
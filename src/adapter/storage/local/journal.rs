@@ -0,0 +1,156 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+// 日志中的记录超过这个数量时，把当前状态写成快照并清空日志
+pub const COMPACT_THRESHOLD: usize = 10000;
+
+// 追加写的日志文件，每行一条 JSON 记录，启动时先加载快照，再按顺序重放快照之后的记录即可恢复状态
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    // 每次压缩加一，日志的第一行记录它属于哪一代
+    generation: u64,
+    records: usize,
+}
+
+// 日志的第一行，旧版本的日志没有这一行，当作第0代
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JournalHeader {
+    generation: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot<S> {
+    generation: u64,
+    state: S,
+}
+
+impl Journal {
+    pub fn open<S: DeserializeOwned, T: DeserializeOwned>(
+        path: PathBuf,
+    ) -> io::Result<(Self, Option<S>, Vec<T>)> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let snapshot_path = Journal::snapshot_path(&path);
+        let (generation, state) = match fs::read(&snapshot_path) {
+            Ok(content) => {
+                let snapshot: Snapshot<S> = serde_json::from_slice(&content)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                (snapshot.generation, Some(snapshot.state))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (0, None),
+            Err(e) => return Err(e),
+        };
+        let mut records = vec![];
+        let mut len = 0;
+        let mut journal_generation = 0;
+        if path.exists() {
+            let content = fs::read(&path)?;
+            for (i, line) in content.split_inclusive(|b| *b == b'\n').enumerate() {
+                // 没有换行符的是崩溃时写了一半的记录，直接丢弃
+                if !line.ends_with(b"\n") {
+                    break;
+                }
+                let body = &line[..line.len() - 1];
+                len += line.len() as u64;
+                if i == 0 {
+                    if let Ok(header) = serde_json::from_slice::<JournalHeader>(body) {
+                        journal_generation = header.generation;
+                        continue;
+                    }
+                }
+                let record = serde_json::from_slice(body)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                records.push(record);
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.set_len(len)?;
+        let mut journal = Journal {
+            path,
+            file,
+            len,
+            generation,
+            records: records.len(),
+        };
+        // 快照写完、日志还没来得及清空时崩溃，日志里的记录已经包含在快照里了
+        if journal_generation < generation || len == 0 {
+            records.clear();
+            journal.reset()?;
+        }
+        Ok((journal, state, records))
+    }
+
+    pub fn append<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        self.write_line(record)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    // 快照之后追加的记录数量，用于判断是否需要压缩
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    // 先写临时文件并落盘，再原子地替换掉旧的快照，最后清空日志
+    pub fn compact<S: Serialize>(&mut self, state: &S) -> io::Result<()> {
+        let snapshot = Snapshot {
+            generation: self.generation + 1,
+            state,
+        };
+        let snapshot_path = Journal::snapshot_path(&self.path);
+        let tmp_path = snapshot_path.with_extension("tmp");
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            tmp.write_all(&serde_json::to_vec(&snapshot)?)?;
+            tmp.sync_all()?;
+        }
+        fs::rename(&tmp_path, &snapshot_path)?;
+        if let Some(dir) = snapshot_path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        self.generation = snapshot.generation;
+        self.reset()
+    }
+
+    // 清空日志，只保留标明当前代数的第一行
+    fn reset(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.len = 0;
+        self.records = 0;
+        self.write_line(&JournalHeader {
+            generation: self.generation,
+        })
+    }
+
+    fn write_line<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let result = self
+            .file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data());
+        match result {
+            Ok(_) => {
+                self.len += line.len() as u64;
+                Ok(())
+            }
+            Err(e) => {
+                // 回滚写了一半的记录，保证日志里只有完整的记录
+                let _ = self.file.set_len(self.len);
+                Err(e)
+            }
+        }
+    }
+
+    fn snapshot_path(path: &PathBuf) -> PathBuf {
+        path.with_extension("snapshot")
+    }
+}
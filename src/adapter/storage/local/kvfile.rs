@@ -0,0 +1,336 @@
+use std::path::PathBuf;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::journal::{Journal, COMPACT_THRESHOLD};
+use crate::{adapter::storage::*, Shared};
+
+const JOURNAL_NAME: &str = "kvfile.journal";
+
+// 持久化的 KVFileStorage，所有写操作先落盘到日志，再交给内存实现处理
+#[derive(Debug, Clone)]
+pub struct LocalKVFileStorage {
+    mem: MemFileKVFileStorage,
+    journal: Shared<Journal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum KVFileRecord {
+    DefineSelector(DefineSelectorParams),
+    RemoveSelector(RemoveSelectorParams),
+    RenameSelector(RenameSelectorParams),
+    DefineAlias(DefineAliasParams),
+    RemoveAlias(RemoveAliasParams),
+    MergeValue(MergeValueParams),
+    AddFile(AddFileParams),
+    RemoveFile(RemoveFileParams),
+    SetLabel(SetLabelParams),
+    AddLabelValue(AddLabelValueParams),
+    RemoveLabelValue(RemoveLabelValueParams),
+    ReplaceLabelValue(ReplaceLabelValueParams),
+}
+
+impl LocalKVFileStorage {
+    pub fn open(dir: &PathBuf) -> std::io::Result<Self> {
+        let (journal, snapshot, records) =
+            Journal::open::<KVFileSnapshot, KVFileRecord>(dir.join(JOURNAL_NAME))?;
+        let mem = match snapshot {
+            Some(s) => MemFileKVFileStorage::from_snapshot(s),
+            None => MemFileKVFileStorage::new(),
+        };
+        // 写入前已经检查过，重放出错说明日志和实现不一致，记下来但不影响启动
+        for record in &records {
+            let result = match record {
+                KVFileRecord::DefineSelector(p) => mem.define_selector(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::RemoveSelector(p) => mem.remove_selector(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::RenameSelector(p) => mem.rename_selector(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::DefineAlias(p) => mem.define_alias(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::RemoveAlias(p) => mem.remove_alias(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::MergeValue(p) => mem.merge_value(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::AddFile(p) => mem.add_file(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::RemoveFile(p) => mem.remove_file(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::SetLabel(p) => mem.set_label(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::AddLabelValue(p) => mem.add_label_value(p).map(|_| ()).map_err(|e| e.to_string()),
+                KVFileRecord::RemoveLabelValue(p) => {
+                    mem.remove_label_value(p).map(|_| ()).map_err(|e| e.to_string())
+                }
+                KVFileRecord::ReplaceLabelValue(p) => {
+                    mem.replace_label_value(p).map(|_| ()).map_err(|e| e.to_string())
+                }
+            };
+            if let Err(e) = result {
+                warn!("replay kvfile record failed, record={:?}, err={}", record, e);
+            }
+        }
+        Ok(LocalKVFileStorage {
+            mem,
+            journal: Shared::new(journal),
+        })
+    }
+
+    // 把当前状态写成快照并清空日志
+    pub fn compact(&self) -> std::io::Result<()> {
+        let mut journal = self.journal.write();
+        journal.compact(&self.mem.snapshot())
+    }
+
+    // 日志太长时压缩，调用方持有日志的锁，保证快照和日志一致；压缩失败不影响已经落盘的修改
+    fn compact_if_needed(&self, journal: &mut Journal) {
+        if journal.records() < COMPACT_THRESHOLD {
+            return;
+        }
+        if let Err(e) = journal.compact(&self.mem.snapshot()) {
+            warn!("compact kvfile journal failed: {}", e);
+        }
+    }
+
+    fn check_file(&self, id: u64) -> Result<(), KVFileStorageError> {
+        match self.mem.contains_file(id) {
+            true => Ok(()),
+            false => Err(KVFileStorageError::NotFound),
+        }
+    }
+}
+
+impl SelectorStorage for LocalKVFileStorage {
+    fn define_selector<'a>(
+        &'a self,
+        params: &'a DefineSelectorParams,
+    ) -> Result<DefineSelectorResult, SelectorStorageError> {
+        params.validate()?;
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&KVFileRecord::DefineSelector(params.clone())) {
+            return Err(SelectorStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.define_selector(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn list_selector<'a>(
+        &'a self,
+        params: &'a ListSelectorParams,
+    ) -> Result<ListSelectorResult, SelectorStorageError> {
+        self.mem.list_selector(params)
+    }
+
+    fn remove_selector<'a>(
+        &'a self,
+        params: &'a RemoveSelectorParams,
+    ) -> Result<RemoveSelectorResult, SelectorStorageError> {
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&KVFileRecord::RemoveSelector(params.clone())) {
+            return Err(SelectorStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.remove_selector(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn rename_selector<'a>(
+        &'a self,
+        params: &'a RenameSelectorParams,
+    ) -> Result<RenameSelectorResult, SelectorStorageError> {
+        params.validate()?;
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&KVFileRecord::RenameSelector(params.clone())) {
+            return Err(SelectorStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.rename_selector(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn define_alias<'a>(
+        &'a self,
+        params: &'a DefineAliasParams,
+    ) -> Result<DefineAliasResult, SelectorStorageError> {
+        let mut journal = self.journal.write();
+        self.mem.check_alias(params)?;
+        if let Err(e) = journal.append(&KVFileRecord::DefineAlias(params.clone())) {
+            return Err(SelectorStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.define_alias(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn remove_alias<'a>(
+        &'a self,
+        params: &'a RemoveAliasParams,
+    ) -> Result<RemoveAliasResult, SelectorStorageError> {
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&KVFileRecord::RemoveAlias(params.clone())) {
+            return Err(SelectorStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.remove_alias(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn list_alias<'a>(
+        &'a self,
+        params: &'a ListAliasParams,
+    ) -> Result<ListAliasResult, SelectorStorageError> {
+        self.mem.list_alias(params)
+    }
+
+    fn merge_value<'a>(
+        &'a self,
+        params: &'a MergeValueParams,
+    ) -> Result<MergeValueResult, SelectorStorageError> {
+        params.validate()?;
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&KVFileRecord::MergeValue(params.clone())) {
+            return Err(SelectorStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.merge_value(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+}
+
+impl KVFileStorage for LocalKVFileStorage {
+    fn list_file<'a>(
+        &'a self,
+        params: &'a ListFileParams,
+    ) -> Result<ListFileResult, KVFileStorageError> {
+        self.mem.list_file(params)
+    }
+
+    fn add_file<'a>(
+        &'a self,
+        params: &'a AddFileParams,
+    ) -> Result<AddFileResult, KVFileStorageError> {
+        // 不合法的请求不写入日志
+        let mut journal = self.journal.write();
+        self.mem.normalize_kvs(&params.label)?;
+        if let Err(e) = journal.append(&KVFileRecord::AddFile(params.clone())) {
+            return Err(KVFileStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.add_file(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn remove_file<'a>(
+        &'a self,
+        params: &'a RemoveFileParams,
+    ) -> Result<RemoveFileResult, KVFileStorageError> {
+        let mut journal = self.journal.write();
+        // 一个都不存在时什么都不用做，也不用写日志
+        if !params.ids.iter().any(|id| self.mem.contains_file(*id)) {
+            return Ok(RemoveFileResult { amount: 0 });
+        }
+        if let Err(e) = journal.append(&KVFileRecord::RemoveFile(params.clone())) {
+            return Err(KVFileStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.remove_file(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn set_label<'a>(
+        &'a self,
+        params: &'a SetLabelParams,
+    ) -> Result<SetLabelResult, KVFileStorageError> {
+        let mut journal = self.journal.write();
+        self.check_file(params.id)?;
+        self.mem.normalize_kvs(&KV::from_hash_map(params.label.clone()))?;
+        if let Err(e) = journal.append(&KVFileRecord::SetLabel(params.clone())) {
+            return Err(KVFileStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.set_label(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn add_label_value<'a>(
+        &'a self,
+        params: &'a AddLabelValueParams,
+    ) -> Result<AddLabelValueResult, KVFileStorageError> {
+        let mut journal = self.journal.write();
+        self.check_file(params.id)?;
+        self.mem.normalize_kvs(&params.label)?;
+        if let Err(e) = journal.append(&KVFileRecord::AddLabelValue(params.clone())) {
+            return Err(KVFileStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.add_label_value(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn remove_label_value<'a>(
+        &'a self,
+        params: &'a RemoveLabelValueParams,
+    ) -> Result<RemoveLabelValueResult, KVFileStorageError> {
+        let mut journal = self.journal.write();
+        self.check_file(params.id)?;
+        KV::validate(&params.label)?;
+        if let Err(e) = journal.append(&KVFileRecord::RemoveLabelValue(params.clone())) {
+            return Err(KVFileStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.remove_label_value(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn replace_label_value<'a>(
+        &'a self,
+        params: &'a ReplaceLabelValueParams,
+    ) -> Result<ReplaceLabelValueResult, KVFileStorageError> {
+        let mut journal = self.journal.write();
+        self.check_file(params.id)?;
+        KV::validate(&params.removed)?;
+        self.mem.normalize_kvs(&params.added)?;
+        if let Err(e) = journal.append(&KVFileRecord::ReplaceLabelValue(params.clone())) {
+            return Err(KVFileStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.replace_label_value(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::storage::testsuite;
+
+    #[test]
+    fn test_local_kv_file() {
+        let dir = testsuite::temp_dir();
+        {
+            let storage = LocalKVFileStorage::open(&dir).unwrap();
+            testsuite::test_kv_file_storage(&storage);
+        }
+        // 重新打开后数据仍然存在
+        {
+            let storage = LocalKVFileStorage::open(&dir).unwrap();
+            let params = ListFileParams {
+                ids: vec![],
+                selectors: vec![],
+                filter: None,
+            };
+            assert_eq!(storage.list_file(&params).unwrap().files.len(), 1);
+            // 压缩之后从快照恢复，结果不变，新的修改继续写到日志里
+            storage.compact().unwrap();
+            let file = storage.list_file(&params).unwrap().files[0].clone();
+            let label = vec![KV::new(String::from("author"), String::from("toriyama"))];
+            storage.add_label_value(&AddLabelValueParams { id: file.id, label }).unwrap();
+        }
+        {
+            let storage = LocalKVFileStorage::open(&dir).unwrap();
+            let params = ListFileParams {
+                ids: vec![],
+                selectors: vec![Selector::new(String::from("author"), vec![String::from("toriyama")])],
+                filter: None,
+            };
+            assert_eq!(storage.list_file(&params).unwrap().files.len(), 1);
+            let params = RemoveLabelValueParams { id: 999, label: vec![] };
+            assert!(matches!(storage.remove_label_value(&params), Err(KVFileStorageError::NotFound)));
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,8 @@
+mod blob;
+mod journal;
+mod kvfile;
+mod selector_set;
+
+pub use blob::*;
+pub use kvfile::*;
+pub use selector_set::*;
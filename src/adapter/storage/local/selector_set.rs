@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::journal::{Journal, COMPACT_THRESHOLD};
+use crate::{adapter::storage::*, Shared};
+
+const JOURNAL_NAME: &str = "selector_set.journal";
+
+// 持久化的 SelectorSetStorage，所有写操作先落盘到日志，再交给内存实现处理
+#[derive(Debug, Clone)]
+pub struct LocalSelectorSetStorage {
+    mem: MemSelectorSetStorage,
+    journal: Shared<Journal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum SelectorSetRecord {
+    DefineSelectorSet(DefineSelectorSetParams),
+    RemoveSelectorSet(RemoveSelectorSetParams),
+    UpdateSelectorSet(UpdateSelectorSetParams),
+}
+
+impl LocalSelectorSetStorage {
+    pub fn open(dir: &PathBuf) -> std::io::Result<Self> {
+        let (journal, snapshot, records) =
+            Journal::open::<Vec<SelectorSet>, SelectorSetRecord>(dir.join(JOURNAL_NAME))?;
+        let mem = match snapshot {
+            Some(s) => MemSelectorSetStorage::from_snapshot(s),
+            None => MemSelectorSetStorage::new(),
+        };
+        // 写入前已经检查过，重放出错说明日志和实现不一致，记下来但不影响启动
+        for record in &records {
+            let result = match record {
+                SelectorSetRecord::DefineSelectorSet(p) => mem.define_selector_set(p).map(|_| ()),
+                SelectorSetRecord::RemoveSelectorSet(p) => mem.remove_selector_set(p).map(|_| ()),
+                SelectorSetRecord::UpdateSelectorSet(p) => mem.update_selector_set(p).map(|_| ()),
+            };
+            if let Err(e) = result {
+                warn!("replay selector set record failed, record={:?}, err={}", record, e);
+            }
+        }
+        Ok(LocalSelectorSetStorage {
+            mem,
+            journal: Shared::new(journal),
+        })
+    }
+
+    // 日志太长时压缩成快照，压缩失败不影响已经落盘的修改
+    fn compact_if_needed(&self, journal: &mut Journal) {
+        if journal.records() < COMPACT_THRESHOLD {
+            return;
+        }
+        if let Err(e) = journal.compact(&self.mem.snapshot()) {
+            warn!("compact selector set journal failed: {}", e);
+        }
+    }
+}
+
+impl SelectorSetStorage for LocalSelectorSetStorage {
+    fn define_selector_set<'a>(
+        &'a self,
+        params: &'a DefineSelectorSetParams,
+    ) -> Result<DefineSelectorSetResult, SelectorSetStorageError> {
+        // 不合法的请求不写入日志
+        params.validate()?;
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&SelectorSetRecord::DefineSelectorSet(params.clone())) {
+            return Err(SelectorSetStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.define_selector_set(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn remove_selector_set<'a>(
+        &'a self,
+        params: &'a RemoveSelectorSetParams,
+    ) -> Result<RemoveSelectorSetResult, SelectorSetStorageError> {
+        let mut journal = self.journal.write();
+        if let Err(e) = journal.append(&SelectorSetRecord::RemoveSelectorSet(params.clone())) {
+            return Err(SelectorSetStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.remove_selector_set(params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn update_selector_set<'a>(
+        &'a self,
+        params: &'a UpdateSelectorSetParams,
+    ) -> Result<UpdateSelectorSetResult, SelectorSetStorageError> {
+        let mut journal = self.journal.write();
+        let mut params = params.clone();
+        params.modified_time = Some(params.modified_time.unwrap_or_else(std::time::SystemTime::now));
+        self.mem.check_update(&params)?;
+        if let Err(e) = journal.append(&SelectorSetRecord::UpdateSelectorSet(params.clone())) {
+            return Err(SelectorSetStorageError::Backend(e.to_string()));
+        }
+        let result = self.mem.update_selector_set(&params);
+        self.compact_if_needed(&mut journal);
+        result
+    }
+
+    fn list_selector_set<'a>(
+        &self,
+        params: &'a ListSelectorSetParams,
+    ) -> Result<ListSelectorSetResult, SelectorSetStorageError> {
+        self.mem.list_selector_set(params)
+    }
+
+    fn list_selector_set_by_prefix<'a>(&self, prefix: &'a String) -> Result<Vec<SelectorSet>, SelectorSetStorageError> {
+        self.mem.list_selector_set_by_prefix(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::storage::testsuite;
+
+    #[test]
+    fn test_local_selector_set() {
+        let dir = testsuite::temp_dir();
+        {
+            let storage = LocalSelectorSetStorage::open(&dir).unwrap();
+            testsuite::test_selector_set_storage(&storage);
+        }
+        // 重新打开后数据仍然存在
+        {
+            let storage = LocalSelectorSetStorage::open(&dir).unwrap();
+            let params = ListSelectorSetParams { names: vec![] };
+            assert_eq!(storage.list_selector_set(&params).unwrap().selector_set.len(), 1);
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
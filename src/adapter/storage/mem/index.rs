@@ -0,0 +1,116 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::adapter::storage::*;
+
+// key -> value -> 带有这个值的文件id，筛选和列出可选值时不用再扫描所有文件
+#[derive(Debug, Default)]
+pub struct LabelIndex {
+    index: HashMap<String, HashMap<String, HashSet<u64>>>,
+}
+
+impl LabelIndex {
+    pub fn insert(&mut self, id: u64, kvs: &Labels) {
+        for (k, vs) in kvs {
+            let values = self.index.entry(k.clone()).or_default();
+            for v in vs {
+                values.entry(v.clone()).or_default().insert(id);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: u64, kvs: &Labels) {
+        for (k, vs) in kvs {
+            let values = match self.index.get_mut(k) {
+                Some(v) => v,
+                None => continue,
+            };
+            for v in vs {
+                if let Some(ids) = values.get_mut(v) {
+                    ids.remove(&id);
+                    if ids.is_empty() {
+                        values.remove(v);
+                    }
+                }
+            }
+            if values.is_empty() {
+                self.index.remove(k);
+            }
+        }
+    }
+
+    // 文件上出现过的所有值，只出现在 excluded 中的文件上的值不算
+    pub fn values(&self, key: &String, excluded: &HashSet<u64>) -> Vec<String> {
+        match self.index.get(key) {
+            Some(values) => values
+                .iter()
+                .filter(|(_, ids)| !ids.is_subset(excluded))
+                .map(|(v, _)| v.clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // 这个值是否出现在 excluded 以外的文件上
+    pub fn contains(&self, key: &String, value: &String, excluded: &HashSet<u64>) -> bool {
+        match self.index.get(key).and_then(|values| values.get(value)) {
+            Some(ids) => !ids.is_subset(excluded),
+            None => false,
+        }
+    }
+
+    // 带有这个key的所有文件
+    pub fn ids(&self, key: &String) -> HashSet<u64> {
+        match self.index.get(key) {
+            Some(values) => values.values().flatten().cloned().collect(),
+            None => HashSet::new(),
+        }
+    }
+
+    // 可能满足条件的文件，否定的条件没法用索引缩小范围，返回None
+    pub fn candidates(&self, selector: &Selector) -> Option<HashSet<u64>> {
+        if selector.is_negative() {
+            return None;
+        }
+        let values = match self.index.get(&selector.key) {
+            Some(v) => v,
+            None => return Some(HashSet::new()),
+        };
+        let ids = match selector.op {
+            SelectorOp::In => selector
+                .value
+                .iter()
+                .filter_map(|v| values.get(v))
+                .flatten()
+                .cloned()
+                .collect(),
+            SelectorOp::Exists => values.values().flatten().cloned().collect(),
+            // 值的数量远小于文件数量，逐个值检查即可
+            _ => values
+                .iter()
+                .filter(|(v, _)| selector.is_match_value(v))
+                .flat_map(|(_, ids)| ids)
+                .cloned()
+                .collect(),
+        };
+        Some(ids)
+    }
+
+    pub fn expr_candidates(&self, expr: &SelectorExpr) -> Option<HashSet<u64>> {
+        match expr {
+            SelectorExpr::Selector(s) => self.candidates(s),
+            SelectorExpr::And(exprs) => exprs
+                .iter()
+                .filter_map(|e| self.expr_candidates(e))
+                .reduce(|a, b| &a & &b),
+            // 只要有一个分支用不了索引，整个或就用不了
+            SelectorExpr::Or(exprs) => {
+                let sets = exprs
+                    .iter()
+                    .map(|e| self.expr_candidates(e))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(sets.into_iter().flatten().collect())
+            }
+            SelectorExpr::Not(_) => None,
+        }
+    }
+}
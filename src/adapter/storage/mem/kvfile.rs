@@ -1,7 +1,9 @@
 use std::collections::{HashMap, HashSet};
 
 use log::{info, debug};
+use serde::{Deserialize, Serialize};
 
+use super::index::LabelIndex;
 use crate::{adapter::storage::*, Shared};
 
 #[derive(Debug, Clone)]
@@ -9,6 +11,12 @@ pub struct MemFileKVFileStorage {
     default_file: Shared<FileItem>,
     files: Shared<HashMap<u64, FileItem>>,
     last_id: Shared<u64>,
+    // key -> 别名 -> 规范值
+    aliases: Shared<HashMap<String, Aliases>>,
+    // 声明过类型的key，其他的key都当作字符串
+    value_types: Shared<HashMap<String, ValueType>>,
+    // 所有文件的标签索引，修改文件标签时同步更新
+    index: Shared<LabelIndex>,
 }
 
 impl MemFileKVFileStorage {
@@ -17,25 +25,134 @@ impl MemFileKVFileStorage {
             default_file: Shared::new(FileItem::new(0)),
             files: Shared::new(HashMap::new()),
             last_id: Shared::new(0),
+            aliases: Shared::new(HashMap::new()),
+            value_types: Shared::new(system_value_types()),
+            index: Shared::new(LabelIndex::default()),
         }
     }
+
+    // 修改文件的标签，同时更新索引
+    fn update_item<R>(&self, item: &mut FileItem, f: impl FnOnce(&mut FileItem) -> R) -> R {
+        let mut index = self.index.write();
+        index.remove(item.id, &item.kvs);
+        let result = f(item);
+        index.insert(item.id, &item.kvs);
+        result
+    }
+
+    // 按声明的类型检查标签，返回转换成保存形式之后的标签
+    pub fn normalize_kvs(&self, kvs: &KVs) -> Result<KVs, KVFileStorageError> {
+        KV::validate(kvs)?;
+        let value_types = self.value_types.read();
+        kvs.iter()
+            .map(|kv| match value_types.get(&kv.key) {
+                Some(t) => t
+                    .normalize(&kv.value)
+                    .map(|v| KV::new(kv.key.clone(), v))
+                    .map_err(KVFileStorageError::InvalidArgument),
+                None => Ok(kv.clone()),
+            })
+            .collect()
+    }
+
+    pub fn contains_file(&self, id: u64) -> bool {
+        self.files.read().contains_key(&id)
+    }
+
+    // 导出完整的状态，用于写快照
+    pub fn snapshot(&self) -> KVFileSnapshot {
+        KVFileSnapshot {
+            default_label: self.default_file.read().kvs.clone(),
+            files: self
+                .files
+                .read()
+                .values()
+                .map(|item| (item.id, item.kvs.clone()))
+                .collect(),
+            last_id: *self.last_id.read(),
+            aliases: self.aliases.read().clone(),
+            value_types: self.value_types.read().clone(),
+        }
+    }
+
+    // 从快照恢复，索引重新建立
+    pub fn from_snapshot(snapshot: KVFileSnapshot) -> Self {
+        let storage = MemFileKVFileStorage::new();
+        storage.default_file.write().kvs = snapshot.default_label;
+        {
+            let mut files = storage.files.write();
+            let mut index = storage.index.write();
+            for (id, kvs) in snapshot.files {
+                index.insert(id, &kvs);
+                files.insert(id, FileItem { id, kvs });
+            }
+        }
+        *storage.last_id.write() = snapshot.last_id;
+        *storage.aliases.write() = snapshot.aliases;
+        storage.value_types.write().extend(snapshot.value_types);
+        storage
+    }
+
+    // 别名不能再有别名，也不能是别人的别名，避免出现链式的映射
+    pub fn check_alias(&self, params: &DefineAliasParams) -> Result<(), SelectorStorageError> {
+        if params.key.is_empty() || params.canonical.is_empty() {
+            return Err(SelectorStorageError::InvalidArgument(String::from(
+                "key and canonical value must not be empty",
+            )));
+        }
+        let aliases = self.aliases.read();
+        let current = aliases.get(&params.key);
+        if current.map_or(false, |a| a.contains_key(&params.canonical)) {
+            return Err(SelectorStorageError::InvalidArgument(format!(
+                "{} is already an alias",
+                params.canonical
+            )));
+        }
+        for alias in &params.aliases {
+            if alias.is_empty()
+                || alias.eq(&params.canonical)
+                || current.map_or(false, |a| a.values().any(|c| c.eq(alias)))
+            {
+                return Err(SelectorStorageError::InvalidArgument(format!(
+                    "{} can not be an alias of {}",
+                    alias, params.canonical
+                )));
+            }
+            // 已经是别的值的别名时，要先删掉才能改成新的值
+            if let Some(canonical) = current.and_then(|a| a.get(alias)) {
+                if !canonical.eq(&params.canonical) {
+                    return Err(SelectorStorageError::InvalidArgument(format!(
+                        "{} is already an alias of {}",
+                        alias, canonical
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// 持久化时保存的完整状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KVFileSnapshot {
+    default_label: Labels,
+    files: HashMap<u64, Labels>,
+    last_id: u64,
+    aliases: HashMap<String, Aliases>,
+    value_types: HashMap<String, ValueType>,
 }
 
 #[derive(Debug, Clone)]
 struct FileItem {
     id: u64,
-    kvs: HashMap<String, String>,
+    kvs: Labels,
 }
 
 impl Into<KVFile> for &FileItem {
     fn into(self) -> KVFile {
         KVFile {
             id: self.id,
-            label: self
-                .kvs
-                .iter()
-                .map(|(k, v)| KV::new(k.clone(), v.clone()))
-                .collect(),
+            label: KV::from_labels(&self.kvs),
         }
     }
 }
@@ -56,25 +173,74 @@ impl FileItem {
             .collect()
     }
 
-    fn to_selector((k, v): (&String, &String)) -> Selector {
-        let mut value = HashSet::new();
-        value.insert(v.clone());
-        Selector {
-            key: k.clone(),
-            value: value,
-        }
+    fn to_selector((k, v): (&String, &Vec<String>)) -> Selector {
+        Selector::new(k.clone(), v.clone())
     }
 
     fn set_labels(&mut self, labels: &HashMap<String, String>) {
         for (k, v) in labels {
-            self.kvs.insert(k.clone(), v.clone());
+            self.kvs.insert(k.clone(), vec![v.clone()]);
+        }
+    }
+
+    fn add_values(&mut self, kvs: &KVs) {
+        for kv in kvs {
+            let values = self.kvs.entry(kv.key.clone()).or_insert_with(Vec::new);
+            if !values.contains(&kv.value) {
+                values.push(kv.value.clone());
+            }
+        }
+    }
+
+    fn remove_values(&mut self, kvs: &KVs) {
+        for kv in kvs {
+            if let Some(values) = self.kvs.get_mut(&kv.key) {
+                values.retain(|v| !v.eq(&kv.value));
+                if values.is_empty() {
+                    self.kvs.remove(&kv.key);
+                }
+            }
+        }
+    }
+
+    fn rename_key(&mut self, key: &String, new_key: &String) -> bool {
+        let values = match self.kvs.remove(key) {
+            Some(v) => v,
+            None => return false,
+        };
+        let merged = self.kvs.entry(new_key.clone()).or_insert_with(Vec::new);
+        for v in values {
+            if !merged.contains(&v) {
+                merged.push(v);
+            }
+        }
+        true
+    }
+
+    // 把values中的值都换成canonical，返回是否有改动
+    fn merge_values(&mut self, key: &String, canonical: &String, values: &Vec<String>) -> bool {
+        let current = match self.kvs.get_mut(key) {
+            Some(v) => v,
+            None => return false,
+        };
+        if !current.iter().any(|v| values.contains(v)) {
+            return false;
+        }
+        let mut merged = vec![];
+        for v in current.iter() {
+            let v = if values.contains(v) { canonical } else { v };
+            if !merged.contains(v) {
+                merged.push(v.clone());
+            }
         }
+        *current = merged;
+        true
     }
 
-    fn get_label(&self, key: &String) -> Option<String> {
+    fn get_label(&self, key: &String) -> Vec<String> {
         match self.kvs.get(key) {
-            Some(v) => Some(v.clone()),
-            None => None,
+            Some(v) => v.clone(),
+            None => vec![],
         }
     }
 }
@@ -84,17 +250,28 @@ impl SelectorStorage for MemFileKVFileStorage {
         &'a self,
         params: &'a DefineSelectorParams,
     ) -> Result<DefineSelectorResult, SelectorStorageError> {
+        params.validate()?;
+        let default_value = params
+            .value_type
+            .normalize(&params.default_value)
+            .map_err(SelectorStorageError::InvalidArgument)?;
+        match params.value_type {
+            ValueType::String => self.value_types.write().remove(&params.key),
+            _ => self
+                .value_types
+                .write()
+                .insert(params.key.clone(), params.value_type.clone()),
+        };
         self.default_file
             .write()
             .kvs
-            .insert(params.key.clone(), params.default_value.clone());
+            .insert(params.key.clone(), vec![default_value.clone()]);
+        let value = match params.set_default_for_history {
+            true => default_value.clone(),
+            false => String::from(""),
+        };
         for (_, item) in self.files.write().iter_mut() {
-            if params.set_default_for_history {
-                item.kvs
-                    .insert(params.key.clone(), params.default_value.clone());
-            } else {
-                item.kvs.insert(params.key.clone(), String::from(""));
-            }
+            self.update_item(item, |item| item.kvs.insert(params.key.clone(), vec![value.clone()]));
         }
         Ok(DefineSelectorResult {})
     }
@@ -107,27 +284,171 @@ impl SelectorStorage for MemFileKVFileStorage {
         {
             let default_file = self.default_file.read();
             for key in &params.key {
-                match default_file.get_label(key) {
-                    Some(v) => {selectors.get_mut(key).unwrap().add_value(v)},
-                    None => {},
+                for v in default_file.get_label(key) {
+                    if params.value.is_empty() || params.value.contains(&v) {
+                        selectors.get_mut(key).unwrap().add_value(v);
+                    }
                 }
             }
         }
         let default_selectors = selectors.iter().map(|(k,v)| v.clone()).collect();
-        for file in self.files.read().iter() {
+        {
+            let index = self.index.read();
+            let excluded: HashSet<u64> = params.exclude_key.iter().flat_map(|k| index.ids(k)).collect();
             for key in &params.key {
-                match file.1.get_label(key) {
-                    Some(v) => {selectors.get_mut(key).unwrap().add_value(v);},
-                    _ => {},
+                // 只查指定的值时逐个查索引，不用列出所有值
+                let values = match params.value.is_empty() {
+                    true => index.values(key, &excluded),
+                    false => params
+                        .value
+                        .iter()
+                        .filter(|v| index.contains(key, v, &excluded))
+                        .cloned()
+                        .collect(),
+                };
+                for v in values {
+                    selectors.get_mut(key).unwrap().add_value(v);
                 }
             }
         }
         debug!("default_selector: {:?} selectors: {:?}", default_selectors, selectors);
+        let value_types = self
+            .value_types
+            .read()
+            .iter()
+            .filter(|(k, _)| params.key.contains(k))
+            .map(|(k, t)| (k.clone(), t.clone()))
+            .collect();
         Ok(ListSelectorResult {
             default_selector: default_selectors,
             selectors: selectors.iter().map(|(_k, v)| v.clone()).collect(),
+            value_types,
         })
     }
+
+    fn remove_selector<'a>(
+        &'a self,
+        params: &'a RemoveSelectorParams,
+    ) -> Result<RemoveSelectorResult, SelectorStorageError> {
+        self.aliases.write().remove(&params.key);
+        self.value_types.write().remove(&params.key);
+        self.default_file.write().kvs.remove(&params.key);
+        let amount = self
+            .files
+            .write()
+            .values_mut()
+            .filter_map(|item| self.update_item(item, |item| item.kvs.remove(&params.key)))
+            .count();
+        Ok(RemoveSelectorResult { amount })
+    }
+
+    fn rename_selector<'a>(
+        &'a self,
+        params: &'a RenameSelectorParams,
+    ) -> Result<RenameSelectorResult, SelectorStorageError> {
+        params.validate()?;
+        {
+            let mut aliases = self.aliases.write();
+            if let Some(old) = aliases.remove(&params.key) {
+                aliases.entry(params.new_key.clone()).or_default().extend(old);
+            }
+        }
+        {
+            // 新key已经声明过类型时以新key的为准
+            let mut value_types = self.value_types.write();
+            if let Some(old) = value_types.remove(&params.key) {
+                value_types.entry(params.new_key.clone()).or_insert(old);
+            }
+        }
+        {
+            // 新key已经有默认值时保留新key的默认值
+            let mut default_file = self.default_file.write();
+            if default_file.kvs.contains_key(&params.new_key) {
+                default_file.kvs.remove(&params.key);
+            } else {
+                default_file.rename_key(&params.key, &params.new_key);
+            }
+        }
+        let amount = self
+            .files
+            .write()
+            .values_mut()
+            .map(|item| self.update_item(item, |item| item.rename_key(&params.key, &params.new_key)))
+            .filter(|renamed| *renamed)
+            .count();
+        Ok(RenameSelectorResult { amount })
+    }
+
+    fn define_alias<'a>(
+        &'a self,
+        params: &'a DefineAliasParams,
+    ) -> Result<DefineAliasResult, SelectorStorageError> {
+        self.check_alias(params)?;
+        let mut aliases = self.aliases.write();
+        let current = aliases.entry(params.key.clone()).or_default();
+        for alias in &params.aliases {
+            current.insert(alias.clone(), params.canonical.clone());
+        }
+        Ok(DefineAliasResult {})
+    }
+
+    fn remove_alias<'a>(
+        &'a self,
+        params: &'a RemoveAliasParams,
+    ) -> Result<RemoveAliasResult, SelectorStorageError> {
+        let mut aliases = self.aliases.write();
+        let amount = match aliases.get_mut(&params.key) {
+            Some(current) => params
+                .aliases
+                .iter()
+                .filter_map(|a| current.remove(a))
+                .count(),
+            None => 0,
+        };
+        Ok(RemoveAliasResult { amount })
+    }
+
+    fn list_alias<'a>(
+        &'a self,
+        params: &'a ListAliasParams,
+    ) -> Result<ListAliasResult, SelectorStorageError> {
+        let aliases = self
+            .aliases
+            .read()
+            .iter()
+            .filter(|(k, _)| params.key.is_empty() || params.key.contains(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        Ok(ListAliasResult { aliases })
+    }
+
+    fn merge_value<'a>(
+        &'a self,
+        params: &'a MergeValueParams,
+    ) -> Result<MergeValueResult, SelectorStorageError> {
+        params.validate()?;
+        self.default_file
+            .write()
+            .merge_values(&params.key, &params.canonical, &params.values);
+        let amount = self
+            .files
+            .write()
+            .values_mut()
+            .map(|item| {
+                self.update_item(item, |item| {
+                    item.merge_values(&params.key, &params.canonical, &params.values)
+                })
+            })
+            .filter(|merged| *merged)
+            .count();
+        // 合并掉的值不再需要作为别名
+        if let Some(current) = self.aliases.write().get_mut(&params.key) {
+            for v in &params.values {
+                current.remove(v);
+            }
+        }
+        Ok(MergeValueResult { amount })
+    }
 }
 
 impl KVFileStorage for MemFileKVFileStorage {
@@ -135,17 +456,39 @@ impl KVFileStorage for MemFileKVFileStorage {
         &'a self,
         params: &'a ListFileParams,
     ) -> Result<ListFileResult, KVFileStorageError> {
-        let files = self
-            .files
-            .read()
-            .iter()
-            .filter(|f| params.ids.is_empty() || params.ids.contains(&f.0))
-            .filter(|f| {
-                params.selectors.is_empty()
-                    || Selector::is_match_selectors(&params.selectors, &f.1.kvs)
-            })
-            .map(|f| f.1.into())
-            .collect();
+        let items = self.files.read();
+        // 先用索引缩小范围，再逐个检查完整的条件
+        let candidates = {
+            let index = self.index.read();
+            let mut sets: Vec<HashSet<u64>> = params
+                .selectors
+                .iter()
+                .filter_map(|s| index.candidates(s))
+                .collect();
+            sets.extend(params.filter.as_ref().and_then(|e| index.expr_candidates(e)));
+            if !params.ids.is_empty() {
+                sets.push(params.ids.iter().cloned().collect());
+            }
+            sets.into_iter().reduce(|a, b| &a & &b)
+        };
+        let is_match = |item: &FileItem| {
+            (params.ids.is_empty() || params.ids.contains(&item.id))
+                && Selector::is_match_selectors(&params.selectors, &item.kvs)
+                && params.filter.as_ref().map_or(true, |e| e.is_match(&item.kvs))
+        };
+        let files = match candidates {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|id| items.get(id))
+                .filter(|item| is_match(*item))
+                .map(|item| item.into())
+                .collect(),
+            None => items
+                .values()
+                .filter(|item| is_match(*item))
+                .map(|item| item.into())
+                .collect(),
+        };
         Ok(ListFileResult { files })
     }
 
@@ -153,12 +496,15 @@ impl KVFileStorage for MemFileKVFileStorage {
         &'a self,
         params: &'a AddFileParams,
     ) -> Result<AddFileResult, KVFileStorageError> {
+        let label = self.normalize_kvs(&params.label)?;
         let mut new_file = self.default_file.read().clone();
-        for kv in &params.label {
-            new_file.kvs.insert(kv.key.clone(), kv.value.clone());
+        // 传入的标签覆盖掉默认值
+        for (k, vs) in KV::to_labels(&label) {
+            new_file.kvs.insert(k, vs);
         }
         *self.last_id.write() += 1;
         new_file.id = *self.last_id.read();
+        self.index.write().insert(new_file.id, &new_file.kvs);
         self.files.write().insert(new_file.id, new_file.clone());
         Ok(AddFileResult {
             id: new_file.id,
@@ -182,7 +528,9 @@ impl KVFileStorage for MemFileKVFileStorage {
             })
             .count();
         for k in params.ids.clone() {
-            self.files.write().remove(&k);
+            if let Some(item) = self.files.write().remove(&k) {
+                self.index.write().remove(item.id, &item.kvs);
+            }
         }
         Ok(RemoveFileResult { amount })
     }
@@ -191,12 +539,183 @@ impl KVFileStorage for MemFileKVFileStorage {
         &'a self,
         params: &'a SetLabelParams,
     ) -> Result<SetLabelResult, KVFileStorageError> {
+        let label = self.normalize_kvs(&KV::from_hash_map(params.label.clone()))?;
+        let label = KV::to_hash_map(&label);
         match self.files.write().get_mut(&params.id) {
             Some(v) => {
-                v.set_labels(&params.label);
+                self.update_item(v, |v| v.set_labels(&label));
                 Ok(SetLabelResult { kvs: v.kvs.clone() })
             }
             None => Err(KVFileStorageError::NotFound),
         }
     }
+
+    fn add_label_value<'a>(
+        &'a self,
+        params: &'a AddLabelValueParams,
+    ) -> Result<AddLabelValueResult, KVFileStorageError> {
+        let label = self.normalize_kvs(&params.label)?;
+        match self.files.write().get_mut(&params.id) {
+            Some(v) => {
+                self.update_item(v, |v| v.add_values(&label));
+                Ok(AddLabelValueResult { kvs: v.kvs.clone() })
+            }
+            None => Err(KVFileStorageError::NotFound),
+        }
+    }
+
+    fn remove_label_value<'a>(
+        &'a self,
+        params: &'a RemoveLabelValueParams,
+    ) -> Result<RemoveLabelValueResult, KVFileStorageError> {
+        // 写入时转换过的值，删除时也要用转换后的形式去匹配
+        let label = self.normalize_kvs(&params.label).unwrap_or_else(|_| params.label.clone());
+        match self.files.write().get_mut(&params.id) {
+            Some(v) => {
+                self.update_item(v, |v| v.remove_values(&label));
+                Ok(RemoveLabelValueResult { kvs: v.kvs.clone() })
+            }
+            None => Err(KVFileStorageError::NotFound),
+        }
+    }
+
+    fn replace_label_value<'a>(
+        &'a self,
+        params: &'a ReplaceLabelValueParams,
+    ) -> Result<ReplaceLabelValueResult, KVFileStorageError> {
+        // 加上的值不合法时什么都不改
+        let added = self.normalize_kvs(&params.added)?;
+        let removed = self.normalize_kvs(&params.removed).unwrap_or_else(|_| params.removed.clone());
+        match self.files.write().get_mut(&params.id) {
+            Some(v) => {
+                self.update_item(v, |v| {
+                    v.remove_values(&removed);
+                    v.add_values(&added);
+                });
+                Ok(ReplaceLabelValueResult { kvs: v.kvs.clone() })
+            }
+            None => Err(KVFileStorageError::NotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::storage::testsuite;
+
+    #[test]
+    fn test_mem_kv_file() {
+        let storage = MemFileKVFileStorage::new();
+        testsuite::test_kv_file_storage(&storage);
+    }
+
+    // 用索引筛选出来的文件和可选值，必须跟逐个扫描所有文件的结果一致
+    fn check_index(storage: &MemFileKVFileStorage) {
+        let keys = vec!["author", "writer", "genre", "volume"];
+        let mut selectors: Vec<Selectors> = vec![vec![]];
+        for key in &keys {
+            let key = key.to_string();
+            selectors.push(vec![Selector::new(key.clone(), vec![String::from("oda"), String::from("3")])]);
+            selectors.push(vec![Selector::with_op(key.clone(), SelectorOp::Exists, vec![])]);
+            selectors.push(vec![Selector::with_op(key.clone(), SelectorOp::NotIn, vec![String::from("oda")])]);
+            selectors.push(vec![Selector::with_op(key.clone(), SelectorOp::Prefix, vec![String::from("to")])]);
+            selectors.push(vec![Selector::with_op(key.clone(), SelectorOp::Empty, vec![])]);
+        }
+        let list = |params: ListFileParams| {
+            let mut ids: Vec<u64> = storage.list_file(&params).unwrap().files.iter().map(|f| f.id).collect();
+            ids.sort();
+            ids
+        };
+        for s in selectors {
+            let mut scanned: Vec<u64> = storage
+                .files
+                .read()
+                .values()
+                .filter(|item| Selector::is_match_selectors(&s, &item.kvs))
+                .map(|item| item.id)
+                .collect();
+            scanned.sort();
+            let by_filter = list(ListFileParams {
+                ids: vec![],
+                selectors: vec![],
+                filter: Some(SelectorExpr::and(&s)),
+            });
+            assert_eq!(by_filter, scanned, "{:?}", s);
+            let by_selectors = list(ListFileParams {
+                ids: vec![],
+                selectors: s.clone(),
+                filter: None,
+            });
+            assert_eq!(by_selectors, scanned, "{:?}", s);
+        }
+        for key in keys {
+            let key = key.to_string();
+            let mut indexed = storage.index.read().values(&key, &HashSet::new());
+            indexed.sort();
+            let mut scanned: Vec<String> = storage
+                .files
+                .read()
+                .values()
+                .flat_map(|item| item.get_label(&key))
+                .collect::<HashSet<String>>()
+                .into_iter()
+                .collect();
+            scanned.sort();
+            assert_eq!(indexed, scanned, "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_label_index() {
+        let storage = MemFileKVFileStorage::new();
+        let files = vec![
+            vec![("author", "oda"), ("genre", "pirate"), ("volume", "3")],
+            vec![("author", "toriyama"), ("genre", "fight")],
+            vec![("author", "Oda"), ("author", "togashi"), ("volume", "10")],
+        ];
+        for labels in files {
+            storage
+                .add_file(&AddFileParams {
+                    label: labels
+                        .iter()
+                        .map(|(k, v)| KV::new(k.to_string(), v.to_string()))
+                        .collect(),
+                })
+                .unwrap();
+        }
+        check_index(&storage);
+        storage
+            .merge_value(&MergeValueParams {
+                key: String::from("author"),
+                canonical: String::from("oda"),
+                values: vec![String::from("Oda")],
+            })
+            .unwrap();
+        check_index(&storage);
+        storage
+            .rename_selector(&RenameSelectorParams {
+                key: String::from("author"),
+                new_key: String::from("writer"),
+            })
+            .unwrap();
+        check_index(&storage);
+        storage
+            .define_selector(&DefineSelectorParams {
+                key: String::from("author"),
+                default_value: String::from("oda"),
+                set_default_for_history: true,
+                value_type: ValueType::String,
+            })
+            .unwrap();
+        check_index(&storage);
+        storage
+            .remove_selector(&RemoveSelectorParams {
+                key: String::from("genre"),
+            })
+            .unwrap();
+        check_index(&storage);
+        storage.remove_file(&RemoveFileParams { ids: vec![1] }).unwrap();
+        check_index(&storage);
+    }
 }
@@ -1,5 +1,6 @@
 mod selector_set;
 mod kvfile;
+mod index;
 
 pub use selector_set::*;
 pub use kvfile::*;
\ No newline at end of file
@@ -1,18 +1,50 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use crate::{adapter::storage::*, Shared};
 
 #[derive(Debug, Clone)]
 pub struct MemSelectorSetStorage {
-    selector_sets: Shared<HashMap<String, SelectorSet>>,
+    // 按名字排序，分组目录可以按前缀查找
+    selector_sets: Shared<BTreeMap<String, SelectorSet>>,
 }
 
 impl MemSelectorSetStorage {
     pub fn new() -> Self {
         Self {
-            selector_sets: Shared::new(HashMap::new()),
+            selector_sets: Shared::new(BTreeMap::new()),
         }
     }
+
+    // 导出所有的筛选器组，用于写快照
+    pub fn snapshot(&self) -> Vec<SelectorSet> {
+        self.selector_sets.read().values().cloned().collect()
+    }
+
+    pub fn from_snapshot(selector_sets: Vec<SelectorSet>) -> Self {
+        Self {
+            selector_sets: Shared::new(selector_sets.into_iter().map(|ss| (ss.name.clone(), ss)).collect()),
+        }
+    }
+
+    // 只检查并计算修改后的结果，不会真正修改
+    pub fn check_update(
+        &self,
+        params: &UpdateSelectorSetParams,
+    ) -> Result<SelectorSet, SelectorSetStorageError> {
+        let selector_sets = self.selector_sets.read();
+        let current = match selector_sets.get(&params.name) {
+            Some(v) => v,
+            None => return Err(SelectorSetStorageError::NotFound),
+        };
+        let updated = current.apply_update(params)?;
+        if updated.name != params.name && selector_sets.contains_key(&updated.name) {
+            return Err(SelectorSetStorageError::Conflict(format!(
+                "selector set {} already exists",
+                updated.name
+            )));
+        }
+        Ok(updated)
+    }
 }
 
 impl SelectorSetStorage for MemSelectorSetStorage {
@@ -20,6 +52,7 @@ impl SelectorSetStorage for MemSelectorSetStorage {
         &'a  self,
         params: &DefineSelectorSetParams,
     ) -> Result<DefineSelectorSetResult, SelectorSetStorageError> {
+        params.validate()?;
         for ss in &params.selector_sets {
             self.selector_sets.write().insert(ss.name.clone(), ss.clone());
         }
@@ -38,6 +71,19 @@ impl SelectorSetStorage for MemSelectorSetStorage {
         })
     }
 
+    fn update_selector_set<'a>(
+        &'a self,
+        params: &'a UpdateSelectorSetParams,
+    ) -> Result<UpdateSelectorSetResult, SelectorSetStorageError> {
+        let updated = self.check_update(params)?;
+        let mut selector_sets = self.selector_sets.write();
+        selector_sets.remove(&params.name);
+        selector_sets.insert(updated.name.clone(), updated.clone());
+        Ok(UpdateSelectorSetResult {
+            selector_set: updated,
+        })
+    }
+
     fn list_selector_set<'a>(
         &'a self,
         params: &ListSelectorSetParams,
@@ -58,51 +104,26 @@ impl SelectorSetStorage for MemSelectorSetStorage {
             selector_set: result,
         })
     }
+
+    fn list_selector_set_by_prefix<'a>(&self, prefix: &'a String) -> Result<Vec<SelectorSet>, SelectorSetStorageError> {
+        Ok(self
+            .selector_sets
+            .read()
+            .range(prefix.clone()..)
+            .take_while(|(name, _)| name.starts_with(prefix.as_str()))
+            .map(|(_, ss)| ss.clone())
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::adapter::storage::testsuite;
 
     #[test]
     fn test_mem_selector_set() {
         let storage = MemSelectorSetStorage::new();
-        // define selector
-        {
-            let mut selector_sets = vec![];
-            selector_sets.push(SelectorSet::new(&String::from("first")));
-            selector_sets.push(SelectorSet::new(&String::from("second")));
-            selector_sets.push(SelectorSet::new(&String::from("third")));
-            let params = DefineSelectorSetParams {
-                selector_sets: selector_sets,
-            };
-            assert!(storage.define_selector_set(&params).is_ok());
-        }
-        // list selector
-        {
-            let params = ListSelectorSetParams{
-                names: vec![String::from("first"), String::from("second"), String::from("third")],
-            };
-            let result = storage.list_selector_set(&params);
-            assert!(result.is_ok());
-            assert_eq!(result.unwrap().selector_set.len(), 3);
-        }
-        // remove selector
-        {
-            let params = RemoveSelectorSetParams{
-                names: vec![String::from("first"), String::from("second")],
-            };
-            let result = storage.remove_selector_set(&params);
-            assert!(result.is_ok());
-        }
-        // list selector
-        {
-            let params = ListSelectorSetParams{
-                names: vec![String::from("first"), String::from("second"), String::from("third")],
-            };
-            let result = storage.list_selector_set(&params);
-            assert!(result.is_ok());
-            assert_eq!(result.unwrap().selector_set.len(), 1);
-        }
+        testsuite::test_selector_set_storage(&storage);
     }
 }
@@ -1,10 +1,20 @@
+mod blob;
 mod kvfile;
+mod query;
 mod selector;
 mod selector_set;
+mod sort;
+pub mod local;
 pub mod mem;
+#[cfg(test)]
+mod testsuite;
 
 
+pub use blob::*;
 pub use selector::*;
 pub use selector_set::*;
 pub use kvfile::*;
+pub use query::*;
+pub use sort::*;
+pub use local::*;
 pub use mem::*;
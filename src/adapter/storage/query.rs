@@ -0,0 +1,414 @@
+use regex::Regex;
+use thiserror::Error;
+
+use super::{order_value, Selector, SelectorExpr, SelectorOp, ValueRange};
+
+// 文件搜索使用的查询语言，例如 author:"Oda" AND volume>=3 -status:dropped
+//
+// 条件之间默认是 AND，可以用 OR、NOT（或者前缀 -、!）以及括号组合，OR 的优先级最低：
+//   key:value      精确匹配，没有引号的 value 中带有 * 或 ? 时按通配符匹配
+//   key:/re/       正则匹配
+//   key:*          存在这个key
+//   key!=value     不等于，key!=* 表示没有这个key
+//   key>v key>=v key<v key<=v    按数字或者日期比较
+//   value          只有值时匹配标题中包含这个值的文件
+
+// 只写了值的条件匹配的key
+const DEFAULT_KEY: &str = "title";
+
+// position 是出错的字符在查询中的下标，从0开始
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{message} at position {position}")]
+pub struct QueryError {
+    pub position: usize,
+    pub message: String,
+}
+
+pub fn parse_query(query: &str) -> Result<SelectorExpr, QueryError> {
+    let mut parser = QueryParser {
+        chars: query.chars().collect(),
+        pos: 0,
+    };
+    parser.skip_whitespace();
+    if parser.is_end() {
+        return Err(parser.error("query is empty"));
+    }
+    let expr = parser.parse_or()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(expr),
+        Some(')') => Err(parser.error("unmatched ')'")),
+        Some(c) => Err(parser.error(&format!("unexpected '{}'", c))),
+    }
+}
+
+// 比较符号，按长度从长到短排列，保证 >= 不会被当成 >
+const OPERATORS: [&str; 7] = ["!=", ">=", "<=", ":", "=", ">", "<"];
+
+struct QueryParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl QueryParser {
+    fn error(&self, message: &str) -> QueryError {
+        self.error_at(self.pos, message)
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> QueryError {
+        QueryError {
+            position,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn is_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        let mut i = self.pos;
+        for c in s.chars() {
+            if self.chars.get(i) != Some(&c) {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    // 关键字必须大写，并且后面跟着空白或者括号，否则当作普通的值
+    fn peek_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        if !self.starts_with(keyword) {
+            return false;
+        }
+        match self.chars.get(self.pos + keyword.chars().count()) {
+            Some(c) => c.is_whitespace() || *c == '(',
+            None => false,
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if !self.peek_keyword(keyword) {
+            return false;
+        }
+        self.pos += keyword.chars().count();
+        true
+    }
+
+    fn parse_or(&mut self) -> Result<SelectorExpr, QueryError> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.eat_keyword("OR") {
+            exprs.push(self.parse_and()?);
+        }
+        Ok(QueryParser::combine(exprs, SelectorExpr::Or))
+    }
+
+    fn parse_and(&mut self) -> Result<SelectorExpr, QueryError> {
+        let mut exprs = vec![self.parse_unary()?];
+        loop {
+            self.skip_whitespace();
+            if self.is_end() || self.peek() == Some(')') || self.peek_keyword("OR") {
+                break;
+            }
+            self.eat_keyword("AND");
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(QueryParser::combine(exprs, SelectorExpr::And))
+    }
+
+    fn combine(
+        mut exprs: Vec<SelectorExpr>,
+        f: fn(Vec<SelectorExpr>) -> SelectorExpr,
+    ) -> SelectorExpr {
+        match exprs.len() {
+            1 => exprs.pop().unwrap(),
+            _ => f(exprs),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<SelectorExpr, QueryError> {
+        self.skip_whitespace();
+        // - 和 ! 只有在条件的开头才表示否定，词中间的 ! 是普通字符
+        if let Some(c @ ('-' | '!')) = self.peek() {
+            self.pos += 1;
+            if self.peek().map_or(true, |c| c.is_whitespace()) {
+                return Err(self.error(&format!("expected condition after '{}'", c)));
+            }
+            return Ok(SelectorExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat_keyword("NOT") {
+            return Ok(SelectorExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<SelectorExpr, QueryError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("unexpected end of query")),
+            Some(')') => Err(self.error("unmatched ')'")),
+            Some('(') => {
+                let start = self.pos;
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(')') {
+                    return Err(self.error("empty parentheses"));
+                }
+                let expr = self.parse_or()?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    return Err(self.error_at(start, "unclosed '('"));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(_) => self.parse_condition(),
+        }
+    }
+
+    fn parse_condition(&mut self) -> Result<SelectorExpr, QueryError> {
+        let start = self.pos;
+        if self.peek() == Some('"') {
+            let value = self.parse_quoted()?;
+            return Ok(QueryParser::title_contains(value));
+        }
+        let key = self.parse_word(|p| OPERATORS.iter().any(|op| p.starts_with(op)));
+        let op = match OPERATORS.iter().find(|op| self.starts_with(op)) {
+            Some(op) => *op,
+            None if key.is_empty() => {
+                let c = self.peek().unwrap_or_default();
+                return Err(self.error(&format!("unexpected '{}'", c)));
+            }
+            None => return Ok(QueryParser::title_contains(key)),
+        };
+        if key.is_empty() {
+            return Err(self.error_at(start, "expected key before operator"));
+        }
+        self.pos += op.chars().count();
+        let value_start = self.pos;
+        let selector = match (op, self.peek()) {
+            (_, None) => return Err(self.error("expected value")),
+            (_, Some(c)) if c.is_whitespace() || c == ')' => {
+                return Err(self.error("expected value"))
+            }
+            (":", Some('/')) => {
+                let pattern = self.parse_regex()?;
+                if let Err(e) = Regex::new(&pattern) {
+                    return Err(self.error_at(value_start, &format!("invalid regex: {}", e)));
+                }
+                Selector::with_op(key, SelectorOp::Regex, vec![pattern])
+            }
+            (_, Some('"')) => {
+                let value = self.parse_quoted()?;
+                self.build_selector(key, op, value, true, value_start)?
+            }
+            _ => {
+                let value = self.parse_word(|_| false);
+                self.build_selector(key, op, value, false, value_start)?
+            }
+        };
+        Ok(SelectorExpr::Selector(selector))
+    }
+
+    fn build_selector(
+        &self,
+        key: String,
+        op: &str,
+        value: String,
+        quoted: bool,
+        value_start: usize,
+    ) -> Result<Selector, QueryError> {
+        let wildcard = !quoted && value.contains(|c: char| c == '*' || c == '?');
+        match op {
+            ":" | "=" if wildcard && value == "*" => {
+                Ok(Selector::with_op(key, SelectorOp::Exists, vec![]))
+            }
+            ":" | "=" if wildcard => Ok(Selector::with_op(key, SelectorOp::Glob, vec![value])),
+            ":" | "=" => Ok(Selector::new(key, vec![value])),
+            "!=" if wildcard && value == "*" => {
+                Ok(Selector::with_op(key, SelectorOp::NotExists, vec![]))
+            }
+            "!=" => Ok(Selector::with_op(key, SelectorOp::NotIn, vec![value])),
+            _ => {
+                if order_value(&value).is_none() {
+                    return Err(self.error_at(
+                        value_start,
+                        &format!("{:?} is not a number or date", value),
+                    ));
+                }
+                let range = match op {
+                    ">" => ValueRange {
+                        min: Some(value),
+                        min_exclusive: true,
+                        ..Default::default()
+                    },
+                    ">=" => ValueRange {
+                        min: Some(value),
+                        ..Default::default()
+                    },
+                    "<" => ValueRange {
+                        max: Some(value),
+                        ..Default::default()
+                    },
+                    _ => ValueRange {
+                        max: Some(value),
+                        max_inclusive: true,
+                        ..Default::default()
+                    },
+                };
+                Ok(Selector {
+                    range: Some(range),
+                    ..Selector::with_op(key, SelectorOp::Range, vec![])
+                })
+            }
+        }
+    }
+
+    // 单独的词按字面匹配标题的一部分，里面的 * ? 不是通配符
+    fn title_contains(value: String) -> SelectorExpr {
+        SelectorExpr::Selector(Selector::with_op(
+            String::from(DEFAULT_KEY),
+            SelectorOp::Regex,
+            vec![regex::escape(&value)],
+        ))
+    }
+
+    // 读到空白、括号或者 stop 返回 true 的位置为止
+    fn parse_word(&mut self, stop: impl Fn(&QueryParser) -> bool) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '(' || c == ')' || c == '"' || stop(self) {
+                break;
+            }
+            word.push(c);
+            self.pos += 1;
+        }
+        word
+    }
+
+    // 引号中可以用 \" 和 \\ 转义
+    fn parse_quoted(&mut self) -> Result<String, QueryError> {
+        self.parse_delimited('"', "unterminated string")
+    }
+
+    // 正则中的 / 需要写成 \/
+    fn parse_regex(&mut self) -> Result<String, QueryError> {
+        self.parse_delimited('/', "unterminated regex")
+    }
+
+    fn parse_delimited(&mut self, delimiter: char, message: &str) -> Result<String, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(start, message)),
+                Some(c) if c == delimiter => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some('\\') => {
+                    match self.chars.get(self.pos + 1) {
+                        Some(c) if *c == delimiter || (delimiter == '"' && *c == '\\') => {
+                            value.push(*c)
+                        }
+                        // 正则里的其他转义原样保留
+                        Some(c) => {
+                            value.push('\\');
+                            value.push(*c);
+                        }
+                        None => return Err(self.error_at(start, message)),
+                    }
+                    self.pos += 2;
+                }
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::storage::Labels;
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        let mut labels = Labels::new();
+        for (k, v) in pairs {
+            labels.entry(k.to_string()).or_default().push(v.to_string());
+        }
+        labels
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let expr = parse_query(r#"author:"Oda" AND volume>=3 -status:dropped"#).unwrap();
+        let file = labels(&[("author", "Oda"), ("volume", "3"), ("status", "reading")]);
+        assert!(expr.is_match(&file));
+        let file = labels(&[("author", "Oda"), ("volume", "3"), ("status", "dropped")]);
+        assert!(!expr.is_match(&file));
+        let file = labels(&[("author", "Oda"), ("volume", "2")]);
+        assert!(!expr.is_match(&file));
+
+        let expr = parse_query("(genre:horror OR genre:thriller) NOT finished:* One").unwrap();
+        let file = labels(&[("genre", "thriller"), ("title", "One Piece")]);
+        assert!(expr.is_match(&file));
+        let file = labels(&[("genre", "thriller"), ("title", "One Piece"), ("finished", "1")]);
+        assert!(!expr.is_match(&file));
+
+        let expr = parse_query(r"title:/^one \/ two$/ volume>3 volume<=5").unwrap();
+        assert!(expr.is_match(&labels(&[("title", "one / two"), ("volume", "5")])));
+        assert!(!expr.is_match(&labels(&[("title", "one / two"), ("volume", "3")])));
+
+        let expr = parse_query(r#""a*b" c.d"#).unwrap();
+        assert!(expr.is_match(&labels(&[("title", "xa*b c.d")])));
+        assert!(!expr.is_match(&labels(&[("title", "axxb c.d")])));
+        assert!(!expr.is_match(&labels(&[("title", "a*b cxd")])));
+
+        // 词中间和末尾的 ! 是普通字符
+        let expr = parse_query("title:Hello!").unwrap();
+        assert!(expr.is_match(&labels(&[("title", "Hello!")])));
+        assert!(!expr.is_match(&labels(&[("title", "Hello")])));
+        let expr = parse_query("Hello! a!b").unwrap();
+        assert!(expr.is_match(&labels(&[("title", "Hello! a!b")])));
+        assert!(!expr.is_match(&labels(&[("title", "Hello a!b")])));
+        let expr = parse_query("status!=dropped !finished:*").unwrap();
+        assert!(expr.is_match(&labels(&[("status", "reading")])));
+        assert!(!expr.is_match(&labels(&[("status", "reading"), ("finished", "1")])));
+    }
+
+    #[test]
+    fn test_parse_query_error() {
+        let cases = vec![
+            ("", 0),
+            ("author:", 7),
+            ("(author:oda", 0),
+            ("author:oda)", 10),
+            (r#"author:"oda"#, 7),
+            ("volume>=abc", 8),
+            ("title:/(/", 6),
+            (":oda", 0),
+            ("- author:oda", 1),
+            ("! author:oda", 1),
+        ];
+        for (query, position) in cases {
+            let err = parse_query(query).unwrap_err();
+            assert_eq!(err.position, position, "{}: {}", query, err);
+        }
+    }
+}
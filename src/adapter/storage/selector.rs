@@ -3,14 +3,27 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     fmt::Debug,
+    num::NonZeroUsize,
+    sync::Mutex,
 };
+
+use lazy_static::lazy_static;
+use lru::LruCache;
+use regex::Regex;
 use thiserror::Error;
 
+use super::{Labels, UNSET_VALUE};
+
 // 定义 SelectorStorage 错误, 用于处理可能出现的错误情况
 #[derive(Error, Debug)]
 pub enum SelectorStorageError {
     #[error("NotFound")]
     NotFound,
+    // 参数不合法，比如空的key或者名字
+    #[error("InvalidArgument({0})")]
+    InvalidArgument(String),
+    #[error("Backend({0})")]
+    Backend(String),
 }
 
 // SelectorStorage trait
@@ -26,8 +39,54 @@ pub trait SelectorStorage: Send + Sync + Debug {
         params: &'a ListSelectorParams,
     ) -> Result<ListSelectorResult, SelectorStorageError>;
 
+    // 从默认值和所有文件上删掉这个key
+    fn remove_selector<'a>(
+        &'a self,
+        params: &'a RemoveSelectorParams,
+    ) -> Result<RemoveSelectorResult, SelectorStorageError>;
+
+    // 默认值和所有文件上的key都会改名，新key已经存在时合并两者的值
+    fn rename_selector<'a>(
+        &'a self,
+        params: &'a RenameSelectorParams,
+    ) -> Result<RenameSelectorResult, SelectorStorageError>;
+
+    // 别名只影响筛选，文件上保存的值保持不变
+    fn define_alias<'a>(
+        &'a self,
+        params: &'a DefineAliasParams,
+    ) -> Result<DefineAliasResult, SelectorStorageError>;
+
+    fn remove_alias<'a>(
+        &'a self,
+        params: &'a RemoveAliasParams,
+    ) -> Result<RemoveAliasResult, SelectorStorageError>;
+
+    fn list_alias<'a>(
+        &'a self,
+        params: &'a ListAliasParams,
+    ) -> Result<ListAliasResult, SelectorStorageError>;
+
+    // 把文件上的这些值都改写成同一个值
+    fn merge_value<'a>(
+        &'a self,
+        params: &'a MergeValueParams,
+    ) -> Result<MergeValueResult, SelectorStorageError>;
+
+    fn get_aliases_by_key<'a>(&'a self, key: &String) -> Result<Aliases, SelectorStorageError> {
+        let params = ListAliasParams {
+            key: vec![key.clone()],
+        };
+        let mut result = self.list_alias(&params)?;
+        Ok(result.aliases.remove(key).unwrap_or_default())
+    }
+
     fn get_selector_by_key<'a>(&'a self, key: String) -> Result<Selector, SelectorStorageError> {
-        match self.list_selector(&ListSelectorParams { key: vec![key] }) {
+        match self.list_selector(&ListSelectorParams {
+            key: vec![key],
+            exclude_key: vec![],
+            value: vec![],
+        }) {
             Ok(res) => match res.selectors.get(0) {
                 Some(v) => Ok(v.clone()),
                 None => Err(SelectorStorageError::NotFound),
@@ -39,6 +98,157 @@ pub trait SelectorStorage: Send + Sync + Debug {
 
 pub type Selectors = Vec<Selector>;
 
+// 同一个key下别名到规范值的映射
+pub type Aliases = HashMap<String, String>;
+
+// 匹配方式，默认是精确匹配其中一个值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectorOp {
+    #[default]
+    In,
+    NotIn,
+    // 只看有没有这个key，不关心value
+    Exists,
+    NotExists,
+    // value中的每一项都是一个表达式，命中任意一个即可
+    Regex,
+    Glob,
+    Prefix,
+    // 按数字或者日期比较，范围由range给出
+    Range,
+    // 没有这个key，或者只有空值
+    Empty,
+}
+
+// 默认下界包含在内，上界不包含，省略表示不限制
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ValueRange {
+    #[serde(default)]
+    pub min: Option<String>,
+    #[serde(default)]
+    pub max: Option<String>,
+    #[serde(default)]
+    pub min_exclusive: bool,
+    #[serde(default)]
+    pub max_inclusive: bool,
+}
+
+// 标签值的类型，默认是不做检查的字符串
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    #[default]
+    String,
+    Int,
+    Float,
+    // 保存为秒级的时间戳，写入时也可以用 2024-01-31 或者 2024-01-31T08:00:00Z
+    Date,
+    Bool,
+    // 只能是列出的这些值之一
+    Enum(Vec<String>),
+}
+
+impl ValueType {
+    // 检查值是否符合类型，并转换成保存时使用的形式，空值表示没有设置，不做检查
+    pub fn normalize(&self, value: &String) -> Result<String, String> {
+        if value.is_empty() {
+            return Ok(value.clone());
+        }
+        let normalized = match self {
+            ValueType::String => Some(value.clone()),
+            ValueType::Int => value.parse::<i64>().ok().map(|v| v.to_string()),
+            ValueType::Float => value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .map(|v| v.to_string()),
+            ValueType::Date => parse_date(value).map(|v| v.to_string()),
+            ValueType::Bool => match value.to_lowercase().as_str() {
+                "true" | "yes" | "1" => Some(String::from("true")),
+                "false" | "no" | "0" => Some(String::from("false")),
+                _ => None,
+            },
+            ValueType::Enum(values) => values.iter().find(|v| v.eq(&value)).cloned(),
+        };
+        normalized.ok_or_else(|| format!("{:?} is not a valid {:?} value", value, self))
+    }
+}
+
+// 1970-01-01 到指定日期的天数
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let yoe = year - era * 400;
+    let month = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * month + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// 闰年的2月有29天
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// 支持时间戳、2024-01-31 以及 2024-01-31T08:00:00Z 三种写法，统一按UTC处理
+pub fn parse_date(value: &str) -> Option<i64> {
+    if let Ok(secs) = value.parse::<i64>() {
+        return Some(secs);
+    }
+    let (date, time) = match value.split_once('T') {
+        Some((d, t)) => (d, Some(t.strip_suffix('Z').unwrap_or(t))),
+        None => (value, None),
+    };
+    let parts: Vec<i64> = date.split('-').map(|p| p.parse::<i64>().ok()).collect::<Option<_>>()?;
+    let (year, month, day) = match parts[..] {
+        [y, m, d] if (1..=12).contains(&m) && (1..=days_in_month(y, m)).contains(&d) => (y, m, d),
+        _ => return None,
+    };
+    let mut secs = days_from_civil(year, month, day) * 24 * 60 * 60;
+    if let Some(time) = time {
+        let parts: Vec<i64> = time.split(':').map(|p| p.parse::<i64>().ok()).collect::<Option<_>>()?;
+        match parts[..] {
+            [h, m, s] if (0..24).contains(&h) && (0..60).contains(&m) && (0..60).contains(&s) => secs += h * 3600 + m * 60 + s,
+            _ => return None,
+        }
+    }
+    Some(secs)
+}
+
+// 范围比较时先按数字解析，再按日期解析
+pub fn order_value(value: &str) -> Option<f64> {
+    match value.parse::<f64>() {
+        Ok(v) => Some(v),
+        Err(_) => parse_date(value).map(|v| v as f64),
+    }
+}
+
+impl ValueRange {
+    pub fn contains(&self, value: &str) -> bool {
+        let value = match order_value(value) {
+            Some(v) => v,
+            None => return false,
+        };
+        let above_min = match self.min.as_ref().and_then(|m| order_value(m)) {
+            Some(min) if self.min_exclusive => value > min,
+            Some(min) => value >= min,
+            None => true,
+        };
+        let below_max = match self.max.as_ref().and_then(|m| order_value(m)) {
+            Some(max) if self.max_inclusive => value <= max,
+            Some(max) => value < max,
+            None => true,
+        };
+        above_min && below_max
+    }
+}
+
 // Selector 的定义
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Selector {
@@ -46,16 +256,102 @@ pub struct Selector {
     // name: String,
     pub key: String,
     pub value: HashSet<String>,
+    #[serde(default)]
+    pub op: SelectorOp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<ValueRange>,
+}
+
+lazy_static! {
+    // 同一个表达式会对每个文件都匹配一次，编译结果缓存起来
+    static ref REGEX_CACHE: Mutex<LruCache<String, Regex>> =
+        Mutex::new(LruCache::new(NonZeroUsize::new(256).unwrap()));
+}
+
+fn cached_regex(pattern: &String) -> Option<Regex> {
+    let mut cache = REGEX_CACHE.lock().unwrap();
+    if let Some(re) = cache.get(pattern) {
+        return Some(re.clone());
+    }
+    let re = Regex::new(pattern).ok()?;
+    cache.put(pattern.clone(), re.clone());
+    Some(re)
+}
+
+// * 匹配任意个字符，? 匹配一个字符
+fn glob_to_regex(glob: &String) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
 }
 
 impl Selector {
     pub fn new(key: String, value: Vec<String>) -> Self {
+        Selector::with_op(key, SelectorOp::In, value)
+    }
+
+    pub fn with_op(key: String, op: SelectorOp, value: Vec<String>) -> Self {
         Selector {
             key,
             value: value.into_iter().collect(),
+            op,
+            range: None,
+        }
+    }
+
+    pub fn with_range(key: String, min: Option<String>, max: Option<String>) -> Self {
+        Selector {
+            key,
+            value: HashSet::new(),
+            op: SelectorOp::Range,
+            range: Some(ValueRange {
+                min,
+                max,
+                ..Default::default()
+            }),
         }
     }
 
+    // 否定的匹配方式对没有这个key的文件也成立
+    pub fn is_negative(&self) -> bool {
+        matches!(
+            self.op,
+            SelectorOp::NotIn | SelectorOp::NotExists | SelectorOp::Empty
+        )
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.key.is_empty() {
+            return Err(String::from("selector key is empty"));
+        }
+        if self.op == SelectorOp::Regex {
+            for pattern in &self.value {
+                if let Err(e) = Regex::new(pattern) {
+                    return Err(format!("invalid regex {}: {}", pattern, e));
+                }
+            }
+        }
+        if self.op == SelectorOp::Range {
+            let range = match &self.range {
+                Some(r) if r.min.is_some() || r.max.is_some() => r,
+                _ => return Err(format!("range of selector {} is empty", self.key)),
+            };
+            for bound in range.min.iter().chain(range.max.iter()) {
+                if order_value(bound).is_none() {
+                    return Err(format!("invalid range bound {}", bound));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn is_missing_value(&self) -> bool {
         self.value.len() == 0
     }
@@ -78,17 +374,72 @@ impl Selector {
         Selector {
             key: self.key.clone(),
             value: value,
+            op: self.op,
+            range: self.range.clone(),
+        }
+    }
+
+    // 把规范值对应的别名也加到筛选条件里，路径中的别名先换成规范值
+    pub fn with_aliases(&self, aliases: &Aliases) -> Selector {
+        if !matches!(self.op, SelectorOp::In | SelectorOp::NotIn) {
+            return self.clone();
+        }
+        let mut value: HashSet<String> = self
+            .value
+            .iter()
+            .map(|v| aliases.get(v).unwrap_or(v).clone())
+            .collect();
+        for (alias, canonical) in aliases {
+            if value.contains(canonical) {
+                value.insert(alias.clone());
+            }
+        }
+        Selector {
+            key: self.key.clone(),
+            value,
+            op: self.op,
+            range: self.range.clone(),
         }
     }
 
-    pub fn is_match(&self, kvs: &HashMap<String, String>) -> bool {
-        match kvs.get(&self.key) {
-            Some(v) => self.value.contains(v),
-            None => false,
+    // 多值标签只要有一个值命中即可，否定的匹配方式要求所有值都不命中
+    pub fn is_match(&self, kvs: &Labels) -> bool {
+        let empty = vec![];
+        let vs = kvs.get(&self.key).unwrap_or(&empty);
+        match self.op {
+            SelectorOp::In => vs.iter().any(|v| self.value.contains(v)),
+            SelectorOp::NotIn => !vs.iter().any(|v| self.value.contains(v)),
+            SelectorOp::Exists => kvs.contains_key(&self.key),
+            SelectorOp::NotExists => !kvs.contains_key(&self.key),
+            SelectorOp::Regex => self
+                .value
+                .iter()
+                .filter_map(cached_regex)
+                .any(|re| vs.iter().any(|v| re.is_match(v))),
+            SelectorOp::Glob => self
+                .value
+                .iter()
+                .filter_map(|g| cached_regex(&glob_to_regex(g)))
+                .any(|re| vs.iter().any(|v| re.is_match(v))),
+            SelectorOp::Prefix => self
+                .value
+                .iter()
+                .any(|p| vs.iter().any(|v| v.starts_with(p.as_str()))),
+            SelectorOp::Range => match &self.range {
+                Some(range) => vs.iter().any(|v| range.contains(v)),
+                None => false,
+            },
+            SelectorOp::Empty => vs.iter().all(|v| v.is_empty()),
         }
     }
 
-    pub fn is_match_selectors(selectors: &Selectors, kvs: &HashMap<String, String>) -> bool {
+    // 只看单个值是否满足条件，用于在索引中按值查找
+    pub fn is_match_value(&self, value: &String) -> bool {
+        let kvs = Labels::from([(self.key.clone(), vec![value.clone()])]);
+        self.is_match(&kvs)
+    }
+
+    pub fn is_match_selectors(selectors: &Selectors, kvs: &Labels) -> bool {
         for s in selectors {
             if !s.is_match(kvs) {
                 return false;
@@ -98,10 +449,101 @@ impl Selector {
     }
 }
 
-// 请求的参数定义
+// 筛选器之间的与、或、非组合，空的与为真，空的或为假
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectorExpr {
+    Selector(Selector),
+    And(Vec<SelectorExpr>),
+    Or(Vec<SelectorExpr>),
+    Not(Box<SelectorExpr>),
+}
+
+impl SelectorExpr {
+    pub fn and(selectors: &Selectors) -> SelectorExpr {
+        SelectorExpr::And(selectors.iter().cloned().map(SelectorExpr::Selector).collect())
+    }
+
+    pub fn is_match(&self, kvs: &Labels) -> bool {
+        match self {
+            SelectorExpr::Selector(s) => s.is_match(kvs),
+            SelectorExpr::And(exprs) => exprs.iter().all(|e| e.is_match(kvs)),
+            SelectorExpr::Or(exprs) => exprs.iter().any(|e| e.is_match(kvs)),
+            SelectorExpr::Not(expr) => !expr.is_match(kvs),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.selectors().iter().try_for_each(|s| s.validate())
+    }
+
+    pub fn selectors(&self) -> Vec<&Selector> {
+        match self {
+            SelectorExpr::Selector(s) => vec![s],
+            SelectorExpr::And(exprs) | SelectorExpr::Or(exprs) => {
+                exprs.iter().flat_map(|e| e.selectors()).collect()
+            }
+            SelectorExpr::Not(expr) => expr.selectors(),
+        }
+    }
+
+    // 保持表达式的结构不变，逐个替换其中的筛选器
+    pub fn try_map<E, F>(&self, f: &mut F) -> Result<SelectorExpr, E>
+    where
+        F: FnMut(&Selector) -> Result<Selector, E>,
+    {
+        Ok(match self {
+            SelectorExpr::Selector(s) => SelectorExpr::Selector(f(s)?),
+            SelectorExpr::And(exprs) => SelectorExpr::And(
+                exprs.iter().map(|e| e.try_map(f)).collect::<Result<_, E>>()?,
+            ),
+            SelectorExpr::Or(exprs) => SelectorExpr::Or(
+                exprs.iter().map(|e| e.try_map(f)).collect::<Result<_, E>>()?,
+            ),
+            SelectorExpr::Not(expr) => SelectorExpr::Not(Box::new(expr.try_map(f)?)),
+        })
+    }
+
+    pub fn rename_key(&self, key: &String, new_key: &String) -> SelectorExpr {
+        let result: Result<SelectorExpr, ()> = self.try_map(&mut |s| {
+            let mut s = s.clone();
+            if s.key.eq(key) {
+                s.key = new_key.clone();
+            }
+            Ok(s)
+        });
+        result.unwrap()
+    }
+
+    // 去掉用到这个key的条件，整个表达式都被去掉时返回None
+    pub fn without_key(&self, key: &String) -> Option<SelectorExpr> {
+        match self {
+            SelectorExpr::Selector(s) if s.key.eq(key) => None,
+            SelectorExpr::Selector(_) => Some(self.clone()),
+            SelectorExpr::And(exprs) | SelectorExpr::Or(exprs) => {
+                let rest: Vec<SelectorExpr> =
+                    exprs.iter().filter_map(|e| e.without_key(key)).collect();
+                match (rest.is_empty(), self) {
+                    (true, _) => None,
+                    (false, SelectorExpr::And(_)) => Some(SelectorExpr::And(rest)),
+                    (false, _) => Some(SelectorExpr::Or(rest)),
+                }
+            }
+            SelectorExpr::Not(expr) => expr.without_key(key).map(|e| SelectorExpr::Not(Box::new(e))),
+        }
+    }
+}
+
+// 请求的参数定义
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ListSelectorParams {
     pub key: Vec<String>,
+    // 带有这些key的文件上的值不列出，比如回收站中的文件
+    #[serde(default)]
+    pub exclude_key: Vec<String>,
+    // 只查这些值是否存在，为空时列出所有值
+    #[serde(default)]
+    pub value: Vec<String>,
     // name: Vec<String>,
 }
 
@@ -110,6 +552,105 @@ pub struct DefineSelectorParams {
     pub key: String,
     pub default_value: String,
     pub set_default_for_history: bool,
+    #[serde(default)]
+    pub value_type: ValueType,
+}
+
+impl DefineSelectorParams {
+    pub fn validate(&self) -> Result<(), SelectorStorageError> {
+        if self.key.is_empty() {
+            return Err(SelectorStorageError::InvalidArgument(String::from(
+                "selector key is empty",
+            )));
+        }
+        if let ValueType::Enum(values) = &self.value_type {
+            if values.is_empty() || values.iter().any(|v| v.is_empty()) {
+                return Err(SelectorStorageError::InvalidArgument(format!(
+                    "invalid enum values of selector {}",
+                    self.key
+                )));
+            }
+        }
+        if self.default_value == UNSET_VALUE {
+            return Err(SelectorStorageError::InvalidArgument(format!(
+                "{} is reserved and can not be the default value of {}",
+                UNSET_VALUE, self.key
+            )));
+        }
+        self.value_type
+            .normalize(&self.default_value)
+            .map_err(SelectorStorageError::InvalidArgument)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSelectorParams {
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameSelectorParams {
+    pub key: String,
+    pub new_key: String,
+}
+
+impl RenameSelectorParams {
+    pub fn validate(&self) -> Result<(), SelectorStorageError> {
+        if self.key.is_empty() || self.new_key.is_empty() || self.key == self.new_key {
+            return Err(SelectorStorageError::InvalidArgument(format!(
+                "can not rename selector {:?} to {:?}",
+                self.key, self.new_key
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefineAliasParams {
+    #[serde(default)]
+    pub key: String,
+    pub canonical: String,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveAliasParams {
+    #[serde(default)]
+    pub key: String,
+    pub aliases: Vec<String>,
+}
+
+// key为空时返回所有key的别名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListAliasParams {
+    pub key: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeValueParams {
+    #[serde(default)]
+    pub key: String,
+    pub canonical: String,
+    pub values: Vec<String>,
+}
+
+impl MergeValueParams {
+    pub fn validate(&self) -> Result<(), SelectorStorageError> {
+        if self.key.is_empty() || self.canonical.is_empty() {
+            return Err(SelectorStorageError::InvalidArgument(String::from(
+                "key and canonical value must not be empty",
+            )));
+        }
+        if self.canonical == UNSET_VALUE {
+            return Err(SelectorStorageError::InvalidArgument(format!(
+                "{} is reserved and can not be a value of {}",
+                UNSET_VALUE, self.key
+            )));
+        }
+        Ok(())
+    }
 }
 
 // 响应的结果定义
@@ -117,7 +658,56 @@ pub struct DefineSelectorParams {
 pub struct ListSelectorResult {
     pub default_selector: Selectors,
     pub selectors: Selectors,
+    // 没有声明类型的key不会出现在这里
+    pub value_types: HashMap<String, ValueType>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefineSelectorResult {}
+
+// 受影响的文件数量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSelectorResult {
+    pub amount: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameSelectorResult {
+    pub amount: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefineAliasResult {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveAliasResult {
+    pub amount: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListAliasResult {
+    pub aliases: HashMap<String, Aliases>,
+}
+
+// 被改写的文件数量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeValueResult {
+    pub amount: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-02"), Some(86400));
+        assert_eq!(parse_date("1970-01-01T01:00:00Z"), Some(3600));
+        assert_eq!(parse_date("2024-02-29"), parse_date("2024-03-01").map(|v| v - 86400));
+        assert!(parse_date("2000-02-29").is_some());
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("1900-02-29"), None);
+        assert_eq!(parse_date("2024-04-31"), None);
+        assert_eq!(parse_date("2024-01-01T-1:00:00"), None);
+    }
+}
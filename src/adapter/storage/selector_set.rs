@@ -1,6 +1,7 @@
 use std::{fmt::Debug, time};
 
-use super::{Selector, Selectors};
+use super::{Selector, SelectorExpr, SelectorOp, Selectors, SortSpec};
+use handlebars::Template;
 use mockall::automock;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -10,15 +11,24 @@ use thiserror::Error;
 pub enum SelectorSetStorageError {
     #[error("NotFound")]
     NotFound,
+    // 参数不合法，比如空的key或者名字
+    #[error("InvalidArgument({0})")]
+    InvalidArgument(String),
+    // 和已有的筛选器组冲突，比如改名时新名字已经被占用
+    #[error("Conflict({0})")]
+    Conflict(String),
+    #[error("Backend({0})")]
+    Backend(String),
 }
 
 // SelectorSetStorage trait
-// TODO:(yangqinglong) add modifier selector_set api
 #[automock]
 pub trait SelectorSetStorage: Send + Sync + Debug {
     fn define_selector_set<'a >(&'a self, params: &'a DefineSelectorSetParams) -> Result<DefineSelectorSetResult, SelectorSetStorageError>;
     
     fn remove_selector_set<'a >(&'a  self, params: &'a RemoveSelectorSetParams) -> Result<RemoveSelectorSetResult, SelectorSetStorageError>;
+
+    fn update_selector_set<'a>(&'a self, params: &'a UpdateSelectorSetParams) -> Result<UpdateSelectorSetResult, SelectorSetStorageError>;
     
     fn list_selector_set<'a>(&self, params: &'a ListSelectorSetParams) -> Result<ListSelectorSetResult, SelectorSetStorageError>;
 
@@ -35,6 +45,16 @@ pub trait SelectorSetStorage: Send + Sync + Debug {
             Err(e) => Err(e),
         }
     }
+
+    // 名字以 prefix 开头的所有筛选器组，默认实现是列出全部再过滤
+    fn list_selector_set_by_prefix<'a>(&self, prefix: &'a String) -> Result<Vec<SelectorSet>, SelectorSetStorageError> {
+        let result = self.list_selector_set(&ListSelectorSetParams { names: vec![] })?;
+        Ok(result
+            .selector_set
+            .into_iter()
+            .filter(|ss| ss.name.starts_with(prefix.as_str()))
+            .collect())
+    }
 }
 
 // SelectorSet 的定义
@@ -44,6 +64,15 @@ pub struct SelectorSet {
     pub static_selectors: Selectors,
     pub dynamic_selectors: Selectors,
     pub modified_time: Option<std::time::SystemTime>,
+    // 和静态筛选器同时生效的组合条件
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<SelectorExpr>,
+    // 文件在列表中显示的名字，handlebars模板，比如 {{series}} v{{volume}} - {{title}}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_template: Option<String>,
+    // 文件列表的排序规则，依次比较，都相同时按标题的自然顺序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<SortSpec>,
 }
 
 impl SelectorSet {
@@ -53,6 +82,9 @@ impl SelectorSet {
             static_selectors: vec![],
             dynamic_selectors: vec![],
             modified_time: Some(time::SystemTime::now()),
+            filter: None,
+            name_template: None,
+            sort: vec![],
         }
     }
 
@@ -67,6 +99,15 @@ impl SelectorSet {
         }
     }
 
+    // 选择没有设置这个标签的文件
+    pub fn add_required_empty(&mut self) {
+        if let Some(index) = self.get_next_required_index() {
+            let selector = self.dynamic_selectors.get_mut(index).unwrap();
+            selector.op = SelectorOp::Empty;
+            selector.add_value(String::new());
+        }
+    }
+
     fn get_next_required_index(&self) -> Option<usize> {
         for (i, s) in self.dynamic_selectors.iter().enumerate() {
             if s.is_missing_value() {
@@ -83,19 +124,173 @@ impl SelectorSet {
         }
     }
 
+    // 在当前定义的基础上应用修改，名字是否冲突由存储判断
+    pub fn apply_update(&self, params: &UpdateSelectorSetParams) -> Result<SelectorSet, SelectorSetStorageError> {
+        let mut result = self.clone();
+        if let Some(name) = &params.new_name {
+            result.name = name.clone();
+        }
+        for key in &params.remove_static_selectors {
+            if !result.static_selectors.iter().any(|s| s.key.eq(key)) {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "static selector {} not found",
+                    key
+                )));
+            }
+            result.static_selectors.retain(|s| !s.key.eq(key));
+        }
+        for selector in &params.add_static_selectors {
+            if result.static_selectors.iter().any(|s| s.key.eq(&selector.key)) {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "static selector {} already exists",
+                    selector.key
+                )));
+            }
+            result.static_selectors.push(selector.clone());
+        }
+        // 新的顺序必须刚好包含原来所有的动态筛选器
+        if let Some(order) = &params.dynamic_selector_order {
+            let mut reordered = vec![];
+            for key in order {
+                match result.dynamic_selectors.iter().find(|s| s.key.eq(key)) {
+                    Some(s) if !reordered.iter().any(|r: &Selector| r.key.eq(key)) => {
+                        reordered.push(s.clone())
+                    }
+                    _ => {
+                        return Err(SelectorSetStorageError::InvalidArgument(format!(
+                            "invalid dynamic selector order: {:?}",
+                            order
+                        )))
+                    }
+                }
+            }
+            if reordered.len() != result.dynamic_selectors.len() {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "invalid dynamic selector order: {:?}",
+                    order
+                )));
+            }
+            result.dynamic_selectors = reordered;
+        }
+        if params.clear_filter {
+            result.filter = None;
+        }
+        if let Some(filter) = &params.filter {
+            result.filter = Some(filter.clone());
+        }
+        if params.clear_name_template {
+            result.name_template = None;
+        }
+        if let Some(template) = &params.name_template {
+            result.name_template = Some(template.clone());
+        }
+        if let Some(sort) = &params.sort {
+            result.sort = sort.clone();
+        }
+        result.modified_time = Some(params.modified_time.unwrap_or_else(time::SystemTime::now));
+        DefineSelectorSetParams {
+            selector_sets: vec![result.clone()],
+        }
+        .validate()?;
+        Ok(result)
+    }
+
 }
 
 // 请求的参数定义
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefineSelectorSetParams {
     pub selector_sets: Vec<SelectorSet>,
 }
 
-#[derive(Debug, Clone)]
+impl DefineSelectorSetParams {
+    // 名字会作为路径的前几段，可以用 '/' 分组，但每一段都不能为空
+    pub fn validate(&self) -> Result<(), SelectorSetStorageError> {
+        for ss in &self.selector_sets {
+            if ss.name.split('/').any(|s| s.is_empty()) {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "invalid selector set name: {:?}",
+                    ss.name
+                )));
+            }
+            for s in ss.static_selectors.iter().chain(ss.dynamic_selectors.iter()) {
+                if let Err(e) = s.validate() {
+                    return Err(SelectorSetStorageError::InvalidArgument(format!(
+                        "{} in selector set {}",
+                        e, ss.name
+                    )));
+                }
+            }
+            if let Some(Err(e)) = ss.filter.as_ref().map(|f| f.validate()) {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "{} in filter of selector set {}",
+                    e, ss.name
+                )));
+            }
+            if let Some(template) = &ss.name_template {
+                if let Err(e) = Template::compile(template) {
+                    return Err(SelectorSetStorageError::InvalidArgument(format!(
+                        "invalid name template of selector set {}: {}",
+                        ss.name, e
+                    )));
+                }
+            }
+            if ss.sort.iter().any(|s| s.key.is_empty()) {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "empty sort key in selector set {}",
+                    ss.name
+                )));
+            }
+            // 动态筛选器的值来自路径，只能精确匹配
+            if ss.dynamic_selectors.iter().any(|s| s.op != SelectorOp::In) {
+                return Err(SelectorSetStorageError::InvalidArgument(format!(
+                    "dynamic selector must use op in, selector set {}",
+                    ss.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoveSelectorSetParams {
     pub names: Vec<String>,
 }
 
+// 只修改传入的部分，其他保持不变
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateSelectorSetParams {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub new_name: Option<String>,
+    #[serde(default)]
+    pub add_static_selectors: Selectors,
+    // 要删除的静态筛选器的key
+    #[serde(default)]
+    pub remove_static_selectors: Vec<String>,
+    // 动态筛选器新的顺序，以key表示
+    #[serde(default)]
+    pub dynamic_selector_order: Option<Vec<String>>,
+    // 替换掉原来的组合条件
+    #[serde(default)]
+    pub filter: Option<SelectorExpr>,
+    #[serde(default)]
+    pub clear_filter: bool,
+    // 替换掉原来的文件名模板
+    #[serde(default)]
+    pub name_template: Option<String>,
+    #[serde(default)]
+    pub clear_name_template: bool,
+    // 替换掉原来的排序规则，传空列表即清空
+    #[serde(default)]
+    pub sort: Option<Vec<SortSpec>>,
+    // 为空时使用当前时间，持久化时会填上，保证重放的结果一致
+    #[serde(default)]
+    pub modified_time: Option<time::SystemTime>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ListSelectorSetParams {
     pub names: Vec<String>,
@@ -111,6 +306,11 @@ pub struct RemoveSelectorSetResult {
     pub names: Vec<String>,
 }
 
+#[derive(Debug, Clone)]
+pub struct UpdateSelectorSetResult {
+    pub selector_set: SelectorSet,
+}
+
 #[derive(Debug, Clone)]
 pub struct ListSelectorSetResult {
     pub selector_set: Vec<SelectorSet>,
@@ -0,0 +1,125 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde::{Deserialize, Serialize};
+
+use super::{KVFile, KV};
+
+// 按加入的先后排序，文件id是递增的
+pub const SORT_BY_ADDED: &str = "_added";
+
+// 列表的排序规则，key 为标签名或者 SORT_BY_ADDED
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortSpec {
+    pub key: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SortSpec {
+    pub fn new(key: &str, descending: bool) -> Self {
+        SortSpec {
+            key: key.to_string(),
+            descending,
+        }
+    }
+
+    pub fn compare(&self, a: &KVFile, b: &KVFile) -> Ordering {
+        let ordering = if self.key == SORT_BY_ADDED {
+            a.id.cmp(&b.id)
+        } else {
+            match (KV::find_value(&a.label, &self.key), KV::find_value(&b.label, &self.key)) {
+                (Some(x), Some(y)) => natural_cmp(&x, &y),
+                // 没有这个标签的文件不论正序倒序都排在最后
+                (Some(_), None) => return Ordering::Less,
+                (None, Some(_)) => return Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+// 自然排序，数字部分按数值比较，其余部分忽略大小写，所以 "Chapter 2" 排在 "Chapter 10" 前面
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut x = a.chars().peekable();
+    let mut y = b.chars().peekable();
+    loop {
+        match (x.peek().cloned(), y.peek().cloned()) {
+            // 只有大小写或者前导0不同时，按原始的字符串比较，保证顺序是确定的
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(c), Some(d)) if c.is_ascii_digit() && d.is_ascii_digit() => {
+                let m = take_number(&mut x);
+                let n = take_number(&mut y);
+                let ordering = m.len().cmp(&n.len()).then_with(|| m.cmp(&n));
+                if ordering.is_ne() {
+                    return ordering;
+                }
+            }
+            (Some(c), Some(d)) => {
+                let ordering = c.to_lowercase().cmp(d.to_lowercase());
+                if ordering.is_ne() {
+                    return ordering;
+                }
+                x.next();
+                y.next();
+            }
+        }
+    }
+}
+
+// 取出连续的数字，去掉前导0之后位数多的数值就大
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits.trim_start_matches('0').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(id: u64, pairs: &[(&str, &str)]) -> KVFile {
+        KVFile {
+            id,
+            label: pairs
+                .iter()
+                .map(|(k, v)| KV::new(k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec!["Chapter 10", "chapter 2", "Chapter 1", "Chapter 02", "Chapter", "Chapter 1a"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["Chapter", "Chapter 1", "Chapter 1a", "Chapter 02", "chapter 2", "Chapter 10"]
+        );
+    }
+
+    #[test]
+    fn test_sort_spec() {
+        let mut files = vec![
+            file(1, &[("volume", "10")]),
+            file(2, &[]),
+            file(3, &[("volume", "9")]),
+        ];
+        files.sort_by(|a, b| SortSpec::new("volume", false).compare(a, b));
+        assert_eq!(files.iter().map(|f| f.id).collect::<Vec<_>>(), vec![3, 1, 2]);
+        files.sort_by(|a, b| SortSpec::new("volume", true).compare(a, b));
+        assert_eq!(files.iter().map(|f| f.id).collect::<Vec<_>>(), vec![1, 3, 2]);
+        files.sort_by(|a, b| SortSpec::new(SORT_BY_ADDED, true).compare(a, b));
+        assert_eq!(files.iter().map(|f| f.id).collect::<Vec<_>>(), vec![3, 2, 1]);
+    }
+}
@@ -0,0 +1,413 @@
+use std::path::PathBuf;
+
+use super::*;
+
+// 各个存储后端共用的测试用例，保证不同实现的行为一致
+
+pub fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("soapdav-{}", uuid::Uuid::new_v4()))
+}
+
+pub fn test_selector_set_storage(storage: &dyn SelectorSetStorage) {
+    // define selector
+    {
+        let mut selector_sets = vec![];
+        selector_sets.push(SelectorSet::new(&String::from("first")));
+        selector_sets.push(SelectorSet::new(&String::from("second")));
+        selector_sets.push(SelectorSet::new(&String::from("third")));
+        let params = DefineSelectorSetParams {
+            selector_sets: selector_sets,
+        };
+        assert!(storage.define_selector_set(&params).is_ok());
+    }
+    // list selector
+    {
+        let params = ListSelectorSetParams{
+            names: vec![String::from("first"), String::from("second"), String::from("third")],
+        };
+        let result = storage.list_selector_set(&params);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().selector_set.len(), 3);
+        // 按名字前缀查找
+        let result = storage.list_selector_set_by_prefix(&String::from("s")).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "second");
+        assert_eq!(storage.list_selector_set_by_prefix(&String::new()).unwrap().len(), 3);
+        assert!(storage.list_selector_set_by_prefix(&String::from("x")).unwrap().is_empty());
+    }
+    // remove selector
+    {
+        let params = RemoveSelectorSetParams{
+            names: vec![String::from("first"), String::from("second")],
+        };
+        let result = storage.remove_selector_set(&params);
+        assert!(result.is_ok());
+    }
+    // list selector
+    {
+        let params = ListSelectorSetParams{
+            names: vec![String::from("first"), String::from("second"), String::from("third")],
+        };
+        let result = storage.list_selector_set(&params);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().selector_set.len(), 1);
+    }
+    // update selector
+    {
+        let params = UpdateSelectorSetParams {
+            name: String::from("third"),
+            new_name: Some(String::from("fourth")),
+            add_static_selectors: vec![Selector::new(String::from("author"), vec![String::from("oda")])],
+            remove_static_selectors: vec![],
+            dynamic_selector_order: None,
+            filter: None,
+            clear_filter: false,
+            name_template: None,
+            clear_name_template: false,
+            sort: None,
+            modified_time: None,
+        };
+        let result = storage.update_selector_set(&params).unwrap();
+        assert_eq!(result.selector_set.static_selectors.len(), 1);
+        assert!(storage.get_selector_set_by_name(&String::from("third")).is_err());
+        assert!(storage.get_selector_set_by_name(&String::from("fourth")).is_ok());
+        assert!(matches!(
+            storage.update_selector_set(&params),
+            Err(SelectorSetStorageError::NotFound)
+        ));
+        // 文件名模板必须能编译
+        let params = UpdateSelectorSetParams {
+            name: String::from("fourth"),
+            new_name: None,
+            add_static_selectors: vec![],
+            name_template: Some(String::from("{{title")),
+            ..params
+        };
+        assert!(matches!(
+            storage.update_selector_set(&params),
+            Err(SelectorSetStorageError::InvalidArgument(_))
+        ));
+    }
+}
+
+pub fn test_kv_file_storage(storage: &dyn KVFileStorage) {
+    let author = String::from("author");
+    // define selector
+    {
+        let params = DefineSelectorParams {
+            key: author.clone(),
+            default_value: String::from("unknown"),
+            set_default_for_history: false,
+            value_type: ValueType::String,
+        };
+        assert!(storage.define_selector(&params).is_ok());
+    }
+    // add file
+    {
+        let params = AddFileParams {
+            label: vec![
+                KV::new(String::from("title"), String::from("first")),
+                KV::new(author.clone(), String::from("oda")),
+            ],
+        };
+        let result = storage.add_file(&params).unwrap();
+        assert_eq!(result.id, 1);
+        let params = AddFileParams {
+            label: vec![KV::new(String::from("title"), String::from("second"))],
+        };
+        let result = storage.add_file(&params).unwrap();
+        assert_eq!(result.id, 2);
+        assert_eq!(result.label.get(&author), Some(&vec![String::from("unknown")]));
+        let params = AddFileParams {
+            label: vec![KV::new(String::new(), String::from("empty"))],
+        };
+        assert!(matches!(
+            storage.add_file(&params),
+            Err(KVFileStorageError::InvalidArgument(_))
+        ));
+    }
+    // list file
+    {
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![Selector::new(author.clone(), vec![String::from("oda")])],
+            filter: None,
+        };
+        let result = storage.list_file(&params).unwrap();
+        assert_eq!(result.files.len(), 1);
+        assert_eq!(result.files[0].id, 1);
+        let title = String::from("title");
+        let cases = vec![
+            (SelectorOp::NotIn, author.clone(), vec!["oda"], 1),
+            (SelectorOp::Exists, author.clone(), vec![], 2),
+            (SelectorOp::NotExists, author.clone(), vec![], 0),
+            (SelectorOp::Prefix, title.clone(), vec!["fir"], 1),
+            (SelectorOp::Glob, title.clone(), vec!["*ond"], 1),
+            (SelectorOp::Regex, title.clone(), vec!["^(first|second)$"], 2),
+        ];
+        for (op, key, value, amount) in cases {
+            let value = value.into_iter().map(String::from).collect();
+            let params = ListFileParams {
+                ids: vec![],
+                selectors: vec![Selector::with_op(key, op, value)],
+                filter: None,
+            };
+            assert_eq!(storage.list_file(&params).unwrap().files.len(), amount, "{:?}", op);
+        }
+        let selector = |key: &String, value: &str| {
+            SelectorExpr::Selector(Selector::new(key.clone(), vec![String::from(value)]))
+        };
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![],
+            filter: Some(SelectorExpr::And(vec![
+                SelectorExpr::Or(vec![selector(&author, "oda"), selector(&author, "unknown")]),
+                SelectorExpr::Not(Box::new(selector(&title, "first"))),
+            ])),
+        };
+        let result = storage.list_file(&params).unwrap();
+        assert_eq!(result.files.len(), 1);
+        assert_eq!(result.files[0].id, 2);
+    }
+    // typed value and range
+    {
+        let released = String::from("released");
+        let params = DefineSelectorParams {
+            key: released.clone(),
+            default_value: String::new(),
+            set_default_for_history: false,
+            value_type: ValueType::Date,
+        };
+        assert!(storage.define_selector(&params).is_ok());
+        let params = SetLabelParams {
+            id: 1,
+            label: [(released.clone(), String::from("yesterday"))].into_iter().collect(),
+        };
+        assert!(matches!(
+            storage.set_label(&params),
+            Err(KVFileStorageError::InvalidArgument(_))
+        ));
+        let params = SetLabelParams {
+            id: 1,
+            label: [(released.clone(), String::from("2024-01-31"))].into_iter().collect(),
+        };
+        let result = storage.set_label(&params).unwrap();
+        assert_eq!(result.kvs.get(&released), Some(&vec![String::from("1706659200")]));
+        let range = |min: &str, max: &str| ListFileParams {
+            ids: vec![],
+            selectors: vec![Selector::with_range(
+                released.clone(),
+                Some(String::from(min)),
+                Some(String::from(max)),
+            )],
+            filter: None,
+        };
+        assert_eq!(storage.list_file(&range("2024-01-01", "2024-02-01")).unwrap().files.len(), 1);
+        assert_eq!(storage.list_file(&range("2024-02-01", "2024-03-01")).unwrap().files.len(), 0);
+        let params = RemoveSelectorParams { key: released };
+        assert_eq!(storage.remove_selector(&params).unwrap().amount, 2);
+        // 大小和修改时间不用声明就有类型
+        let body_size = String::from(BODY_SIZE_KEY);
+        let params = SetLabelParams {
+            id: 1,
+            label: [(body_size.clone(), String::from("big"))].into_iter().collect(),
+        };
+        assert!(matches!(
+            storage.set_label(&params),
+            Err(KVFileStorageError::InvalidArgument(_))
+        ));
+        for (id, size) in [(1, "5"), (2, "10")] {
+            let params = SetLabelParams {
+                id,
+                label: [(body_size.clone(), String::from(size))].into_iter().collect(),
+            };
+            storage.set_label(&params).unwrap();
+        }
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![Selector::with_range(
+                body_size.clone(),
+                Some(String::from("2")),
+                Some(String::from("10")),
+            )],
+            filter: None,
+        };
+        let result = storage.list_file(&params).unwrap();
+        assert_eq!(result.files.len(), 1);
+        assert_eq!(result.files[0].id, 1);
+        let params = ListSelectorParams {
+            key: vec![String::from(MODIFIED_TIME_KEY)],
+            exclude_key: vec![],
+            value: vec![],
+        };
+        let result = storage.list_selector(&params).unwrap();
+        assert_eq!(result.value_types.get(MODIFIED_TIME_KEY), Some(&ValueType::Date));
+    }
+    // set label
+    {
+        let params = SetLabelParams {
+            id: 2,
+            label: [(author.clone(), String::from("toriyama"))].into_iter().collect(),
+        };
+        assert!(storage.set_label(&params).is_ok());
+        let params = SetLabelParams {
+            id: 100,
+            label: params.label.clone(),
+        };
+        assert!(storage.set_label(&params).is_err());
+    }
+    // add and remove label value
+    {
+        let params = AddLabelValueParams {
+            id: 2,
+            label: vec![KV::new(author.clone(), String::from("oda"))],
+        };
+        let result = storage.add_label_value(&params).unwrap();
+        assert_eq!(result.kvs.get(&author).unwrap().len(), 2);
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![Selector::new(author.clone(), vec![String::from("oda")])],
+            filter: None,
+        };
+        assert_eq!(storage.list_file(&params).unwrap().files.len(), 2);
+        let params = RemoveLabelValueParams {
+            id: 2,
+            label: vec![KV::new(author.clone(), String::from("oda"))],
+        };
+        let result = storage.remove_label_value(&params).unwrap();
+        assert_eq!(result.kvs.get(&author), Some(&vec![String::from("toriyama")]));
+        // 替换时加上的值不合法，删除的部分也不会生效
+        let params = ReplaceLabelValueParams {
+            id: 2,
+            removed: vec![KV::new(author.clone(), String::from("toriyama"))],
+            added: vec![KV::new(String::new(), String::from("oda"))],
+        };
+        assert!(storage.replace_label_value(&params).is_err());
+        let params = ReplaceLabelValueParams {
+            added: vec![KV::new(author.clone(), String::from("oda"))],
+            ..params
+        };
+        let result = storage.replace_label_value(&params).unwrap();
+        assert_eq!(result.kvs.get(&author), Some(&vec![String::from("oda")]));
+        let params = ReplaceLabelValueParams {
+            id: 2,
+            removed: vec![KV::new(author.clone(), String::from("oda"))],
+            added: vec![KV::new(author.clone(), String::from("toriyama"))],
+        };
+        storage.replace_label_value(&params).unwrap();
+    }
+    // list selector
+    {
+        let params = ListSelectorParams {
+            key: vec![author.clone()],
+            exclude_key: vec![],
+            value: vec![],
+        };
+        let result = storage.list_selector(&params).unwrap();
+        assert_eq!(result.selectors.len(), 1);
+        assert!(result.selectors[0].value.contains("oda"));
+        assert!(result.selectors[0].value.contains("toriyama"));
+        // 带有排除的key的文件上的值不列出，默认值不受影响
+        let params = ListSelectorParams {
+            exclude_key: vec![String::from("title")],
+            ..params
+        };
+        let result = storage.list_selector(&params).unwrap();
+        assert!(!result.selectors[0].value.contains("oda"));
+        assert!(result.selectors[0].value.contains("unknown"));
+        // 只查指定的值
+        let params = ListSelectorParams {
+            exclude_key: vec![],
+            value: vec![String::from("oda"), String::from("nobody")],
+            ..params
+        };
+        let result = storage.list_selector(&params).unwrap();
+        assert_eq!(result.selectors[0].value.len(), 1);
+        assert!(result.selectors[0].value.contains("oda"));
+    }
+    // remove file
+    {
+        let params = RemoveFileParams { ids: vec![1, 100] };
+        assert_eq!(storage.remove_file(&params).unwrap().amount, 1);
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![],
+            filter: None,
+        };
+        assert_eq!(storage.list_file(&params).unwrap().files.len(), 1);
+    }
+    // alias and merge value
+    {
+        let params = DefineAliasParams {
+            key: author.clone(),
+            canonical: String::from("toriyama"),
+            aliases: vec![String::from("Toriyama Akira")],
+        };
+        assert!(storage.define_alias(&params).is_ok());
+        let aliases = storage.get_aliases_by_key(&author).unwrap();
+        assert_eq!(aliases.get("Toriyama Akira"), Some(&String::from("toriyama")));
+        let params = DefineAliasParams {
+            key: author.clone(),
+            canonical: String::from("Toriyama Akira"),
+            aliases: vec![String::from("akira")],
+        };
+        assert!(storage.define_alias(&params).is_err());
+        // 别名不能直接改成另一个值的别名，重复定义成同一个值没关系
+        let params = DefineAliasParams {
+            key: author.clone(),
+            canonical: String::from("oda"),
+            aliases: vec![String::from("Toriyama Akira")],
+        };
+        assert!(storage.define_alias(&params).is_err());
+        let params = DefineAliasParams {
+            key: author.clone(),
+            canonical: String::from("toriyama"),
+            aliases: vec![String::from("Toriyama Akira")],
+        };
+        assert!(storage.define_alias(&params).is_ok());
+        let aliases = storage.get_aliases_by_key(&author).unwrap();
+        assert_eq!(aliases.get("Toriyama Akira"), Some(&String::from("toriyama")));
+        let params = AddLabelValueParams {
+            id: 2,
+            label: vec![KV::new(author.clone(), String::from("鳥山明"))],
+        };
+        storage.add_label_value(&params).unwrap();
+        let params = MergeValueParams {
+            key: author.clone(),
+            canonical: String::from("toriyama"),
+            values: vec![String::from("鳥山明")],
+        };
+        assert_eq!(storage.merge_value(&params).unwrap().amount, 1);
+        let params = ListFileParams {
+            ids: vec![2],
+            selectors: vec![],
+            filter: None,
+        };
+        let file = storage.list_file(&params).unwrap().files.remove(0);
+        assert_eq!(KV::find_values(&file.label, &author), vec![String::from("toriyama")]);
+    }
+    // rename and remove selector
+    {
+        let writer = String::from("writer");
+        let params = RenameSelectorParams {
+            key: author.clone(),
+            new_key: writer.clone(),
+        };
+        assert_eq!(storage.rename_selector(&params).unwrap().amount, 1);
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![Selector::new(writer.clone(), vec![String::from("toriyama")])],
+            filter: None,
+        };
+        assert_eq!(storage.list_file(&params).unwrap().files.len(), 1);
+        let params = RemoveSelectorParams { key: writer.clone() };
+        assert_eq!(storage.remove_selector(&params).unwrap().amount, 1);
+        let params = AddFileParams {
+            label: vec![KV::new(String::from("title"), String::from("third"))],
+        };
+        let result = storage.add_file(&params).unwrap();
+        assert!(result.label.get(&writer).is_none());
+        assert!(result.label.get(&author).is_none());
+        storage.remove_file(&RemoveFileParams { ids: vec![result.id] }).unwrap();
+    }
+}
@@ -1,11 +1,11 @@
-use std::fs::File;
+use std::collections::HashMap;
 
 use thiserror::Error;
-use webdav_handler::fs::DavFileSystem;
+use webdav_handler::fs::{DavFileSystem, FsError};
 use serde::{Deserialize, Serialize};
 
 
-use crate::adapter::storage::{self, KVFileStorageError, SelectorSet, SelectorSetStorageError, SelectorStorageError, KV};
+use crate::adapter::storage::{self, BlobStorageError, KVFile, KVFileStorageError, Labels, SelectorSet, SelectorSetStorageError, SelectorStorageError, SelectorExpr, Selectors, ValueType, KV};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefineCollectionParams {
@@ -24,14 +24,93 @@ pub struct RemoveCollectionParams {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoveCollectionResult {}
 
+pub type UpdateCollectionParams = storage::UpdateSelectorSetParams;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCollectionResult {
+    pub collection: SelectorSet,
+}
+
+// names为空时返回所有的筛选器组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListCollectionParams {
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListCollectionResult {
+    pub collections: Vec<SelectorSet>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddFileParams {
     pub kvs: Vec<KV>,
 }
 
+// 合并了默认值之后的标签
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddFileResult {
+    pub id: u64,
+    pub label: Labels,
+}
 
+// 回收站中的文件不会出现在结果里
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFileParams {
+    pub ids: Vec<u64>,
+    pub selectors: Selectors,
+    #[serde(default)]
+    pub filter: Option<SelectorExpr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFileResult {
+    pub files: Vec<KVFile>,
+}
+
+// 和 WebDAV 的 DELETE 一样，只是把文件放到回收站
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveFileParams {
+    pub ids: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveFileResult {
+    pub amount: usize,
+}
+
+// 用新的值替换掉key原有的所有值，值为空时删除这个key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLabelParams {
+    #[serde(default)]
+    pub id: u64,
+    pub label: Labels,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLabelResult {
+    pub file: KVFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreFileParams {
+    pub ids: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreFileResult {
+    pub amount: usize,
+}
+
+// 只会彻底删除已经在回收站中的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeFileParams {
+    pub ids: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeFileResult {
+    pub amount: usize,
 }
 
 pub type DefineSelectorParams = storage::DefineSelectorParams;
@@ -39,25 +118,115 @@ pub type DefineSelectorParams = storage::DefineSelectorParams;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefineSelectorResult {}
 
+// 还有筛选器组引用这个key时，cascade为true会一并修改这些筛选器组，否则拒绝修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSelectorParams {
+    #[serde(default)]
+    pub key: String,
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+// amount为受影响的文件数量，collections为被一并修改的筛选器组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSelectorResult {
+    pub amount: usize,
+    pub collections: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameSelectorParams {
+    #[serde(default)]
+    pub key: String,
+    pub new_key: String,
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameSelectorResult {
+    pub amount: usize,
+    pub collections: Vec<String>,
+}
+
+pub type ListSelectorParams = storage::ListSelectorParams;
+
+// 别名和合并都只对同一个key下的值生效
+pub type DefineAliasParams = storage::DefineAliasParams;
+pub type DefineAliasResult = storage::DefineAliasResult;
+pub type RemoveAliasParams = storage::RemoveAliasParams;
+pub type RemoveAliasResult = storage::RemoveAliasResult;
+pub type ListAliasParams = storage::ListAliasParams;
+pub type ListAliasResult = storage::ListAliasResult;
+pub type MergeValueParams = storage::MergeValueParams;
+pub type MergeValueResult = storage::MergeValueResult;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSelectorResult {
+    pub selectors: Selectors,
+    pub value_types: HashMap<String, ValueType>,
+}
+
 
 
 pub trait CollectionFS:DavFileSystem {
     fn add_file<'a>(&'a self, params: &'a AddFileParams) -> Result<AddFileResult, FilesystemError>;
     fn define_selector<'a >(&'a  self, params: &'a DefineSelectorParams) -> Result<DefineSelectorResult, FilesystemError>;
+    fn remove_selector<'a>(&'a self, params: &'a RemoveSelectorParams) -> Result<RemoveSelectorResult, FilesystemError>;
+    fn rename_selector<'a>(&'a self, params: &'a RenameSelectorParams) -> Result<RenameSelectorResult, FilesystemError>;
+    fn define_alias<'a>(&'a self, params: &'a DefineAliasParams) -> Result<DefineAliasResult, FilesystemError>;
+    fn remove_alias<'a>(&'a self, params: &'a RemoveAliasParams) -> Result<RemoveAliasResult, FilesystemError>;
+    fn list_alias<'a>(&'a self, params: &'a ListAliasParams) -> Result<ListAliasResult, FilesystemError>;
+    fn merge_value<'a>(&'a self, params: &'a MergeValueParams) -> Result<MergeValueResult, FilesystemError>;
     fn define_collection<'a >(&'a self, params: &'a DefineCollectionParams) -> Result<DefineCollectionResult, FilesystemError>;
     fn remove_collection<'a >(&'a self, params: &'a RemoveCollectionParams) -> Result<RemoveCollectionResult, FilesystemError>;
+    fn update_collection<'a>(&'a self, params: &'a UpdateCollectionParams) -> Result<UpdateCollectionResult, FilesystemError>;
+    fn list_collection<'a>(&'a self, params: &'a ListCollectionParams) -> Result<ListCollectionResult, FilesystemError>;
+    fn list_selector<'a>(&'a self, params: &'a ListSelectorParams) -> Result<ListSelectorResult, FilesystemError>;
+    fn list_file<'a>(&'a self, params: &'a ListFileParams) -> Result<ListFileResult, FilesystemError>;
+    fn remove_file<'a>(&'a self, params: &'a RemoveFileParams) -> Result<RemoveFileResult, FilesystemError>;
+    fn set_label<'a>(&'a self, params: &'a SetLabelParams) -> Result<SetLabelResult, FilesystemError>;
+    fn restore_file<'a>(&'a self, params: &'a RestoreFileParams) -> Result<RestoreFileResult, FilesystemError>;
+    fn purge_file<'a>(&'a self, params: &'a PurgeFileParams) -> Result<PurgeFileResult, FilesystemError>;
 }
 
 #[derive(Debug, Clone, Error)]
 pub enum FilesystemError {
     #[error("NotFound")]
     NotFound,
+    // 参数不合法，比如缺少标题、key为空或者修改了由文件系统维护的标签
+    #[error("InvalidArgument({0})")]
+    InvalidArgument(String),
+    // 和已有的数据冲突，比如使用了保留的名字
+    #[error("Conflict({0})")]
+    Conflict(String),
+    // 同一个目录下不允许出现两个同名的文件
+    #[error("DuplicateTitle({0})")]
+    DuplicateTitle(String),
+    // 存储后端的读写错误
+    #[error("Backend({0})")]
+    Backend(String),
+}
+
+impl FilesystemError {
+    // 错误类型的简短描述，用于接口返回
+    pub fn code(&self) -> &'static str {
+        match self {
+            FilesystemError::NotFound => "not_found",
+            FilesystemError::InvalidArgument(_) => "invalid_argument",
+            FilesystemError::Conflict(_) => "conflict",
+            FilesystemError::DuplicateTitle(_) => "duplicate_title",
+            FilesystemError::Backend(_) => "backend",
+        }
+    }
 }
 
 impl From<SelectorStorageError> for FilesystemError {
     fn from(value: SelectorStorageError) -> Self {
         match value {
             SelectorStorageError::NotFound => FilesystemError::NotFound,
+            SelectorStorageError::InvalidArgument(e) => FilesystemError::InvalidArgument(e),
+            SelectorStorageError::Backend(e) => FilesystemError::Backend(e),
         }
     }
 }
@@ -66,6 +235,8 @@ impl From<KVFileStorageError> for FilesystemError {
     fn from(value: KVFileStorageError) -> Self {
         match value {
             KVFileStorageError::NotFound => FilesystemError::NotFound,
+            KVFileStorageError::InvalidArgument(e) => FilesystemError::InvalidArgument(e),
+            KVFileStorageError::Backend(e) => FilesystemError::Backend(e),
         }
     }
 }
@@ -74,6 +245,54 @@ impl From<SelectorSetStorageError> for FilesystemError {
     fn from(value: SelectorSetStorageError) -> Self {
         match value {
             SelectorSetStorageError::NotFound => FilesystemError::NotFound,
+            SelectorSetStorageError::InvalidArgument(e) => FilesystemError::InvalidArgument(e),
+            SelectorSetStorageError::Conflict(e) => FilesystemError::Conflict(e),
+            SelectorSetStorageError::Backend(e) => FilesystemError::Backend(e),
+        }
+    }
+}
+
+impl From<BlobStorageError> for FilesystemError {
+    fn from(value: BlobStorageError) -> Self {
+        match value {
+            BlobStorageError::NotFound => FilesystemError::NotFound,
+            BlobStorageError::Backend(e) => FilesystemError::Backend(e),
         }
     }
-}
\ No newline at end of file
+}
+
+// WebDAV 只能返回固定的几种错误，参数错误统一当成没有权限
+impl From<FilesystemError> for FsError {
+    fn from(value: FilesystemError) -> Self {
+        match value {
+            FilesystemError::NotFound => FsError::NotFound,
+            FilesystemError::InvalidArgument(_) => FsError::Forbidden,
+            FilesystemError::Conflict(_) | FilesystemError::DuplicateTitle(_) => FsError::Exists,
+            FilesystemError::Backend(_) => FsError::GeneralFailure,
+        }
+    }
+}
+
+impl From<SelectorStorageError> for FsError {
+    fn from(value: SelectorStorageError) -> Self {
+        FsError::from(FilesystemError::from(value))
+    }
+}
+
+impl From<KVFileStorageError> for FsError {
+    fn from(value: KVFileStorageError) -> Self {
+        FsError::from(FilesystemError::from(value))
+    }
+}
+
+impl From<SelectorSetStorageError> for FsError {
+    fn from(value: SelectorSetStorageError) -> Self {
+        FsError::from(FilesystemError::from(value))
+    }
+}
+
+impl From<BlobStorageError> for FsError {
+    fn from(value: BlobStorageError) -> Self {
+        FsError::from(FilesystemError::from(value))
+    }
+}
@@ -1,4 +1,21 @@
+use crate::adapter::storage::{BODY_SIZE_KEY, MODIFIED_TIME_KEY, UNSET_VALUE};
+
 pub const TITLE: &str = "title";
-pub const BODY_SIZE: &str = "body_size";
-pub const MODIFIED_TIME: &str = "modified_time";
-pub static BASIC_META_KEYS: [&'static str; 3] = [TITLE, BODY_SIZE, MODIFIED_TIME];
+pub const BODY_SIZE: &str = BODY_SIZE_KEY;
+pub const MODIFIED_TIME: &str = MODIFIED_TIME_KEY;
+// 带有这个标签的文件处于回收站中，值为放入回收站的时间
+pub const TRASHED_TIME: &str = "trashed_time";
+pub static BASIC_META_KEYS: [&'static str; 4] = [TITLE, BODY_SIZE, MODIFIED_TIME, TRASHED_TIME];
+
+// 根目录下的回收站
+pub const TRASH_DIR: &str = ".trash";
+// 根目录下的临时查询，/_query/<查询>/ 列出满足查询的文件
+pub const QUERY_DIR: &str = "_query";
+// 筛选目录下没有设置这个标签的文件
+pub const UNSET_DIR: &str = UNSET_VALUE;
+// 每个文件旁边的标签目录，"<文件名>.labels" 下面是这个文件的标签文件
+pub const LABEL_DIR_SUFFIX: &str = ".labels";
+
+// PROPFIND/PROPPATCH 中标签所在的命名空间
+pub const LABEL_NAMESPACE: &str = "urn:soapdav:label";
+pub const LABEL_PREFIX: &str = "soap";
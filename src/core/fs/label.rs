@@ -0,0 +1,16 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 时间类的标签统一以秒级的unix时间戳保存
+pub fn format_time(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .to_string()
+}
+
+pub fn parse_time(value: &str) -> Option<SystemTime> {
+    match value.parse::<u64>() {
+        Ok(secs) => UNIX_EPOCH.checked_add(Duration::from_secs(secs)),
+        Err(_) => None,
+    }
+}
@@ -1,7 +1,9 @@
 mod collectionfs;
 mod simplefs;
 mod consts;
+mod label;
 
 pub use collectionfs::*;
 pub use simplefs::*;
-pub use consts::*;
\ No newline at end of file
+pub use consts::*;
+pub use label::*;
@@ -0,0 +1,268 @@
+use std::cmp::{max, min};
+use std::io::SeekFrom;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use bytes::{Buf, Bytes};
+use futures::FutureExt;
+use webdav_handler::fs::{DavFile, DavMetaData, FsError};
+
+use crate::adapter::storage::{
+    BlobStorage, BlobStorageError, CommitBlobParams, CreateBlobParams, KVFileStorage,
+    ReadBlobParams, SetLabelParams, StatBlobParams, WriteBlobParams,
+};
+use crate::core::fs::{format_time, BODY_SIZE, MODIFIED_TIME};
+
+// 文件本体，按需从 BlobStorage 中读写，不会一次性加载到内存
+#[derive(Debug, Clone)]
+pub struct BlobFile {
+    blob: Arc<dyn BlobStorage>,
+    // 只有以写模式打开时才有，用于在 flush 时更新文件大小等标签
+    kv_file: Option<Arc<dyn KVFileStorage>>,
+    id: u64,
+    size: u64,
+    modified_time: SystemTime,
+    offset: u64,
+    // 写模式下正在写的临时文件本体，flush 时替换掉正式的文件本体
+    temp: Option<String>,
+}
+
+impl BlobFile {
+    pub fn open(blob: Arc<dyn BlobStorage>, id: u64) -> Result<Self, BlobStorageError> {
+        let stat = blob.stat_blob(&StatBlobParams { id })?;
+        Ok(BlobFile {
+            blob,
+            kv_file: None,
+            id,
+            size: stat.size,
+            modified_time: stat.modified_time,
+            offset: 0,
+            temp: None,
+        })
+    }
+
+    pub fn create(
+        blob: Arc<dyn BlobStorage>,
+        kv_file: Arc<dyn KVFileStorage>,
+        id: u64,
+        truncate: bool,
+        append: bool,
+    ) -> Result<Self, BlobStorageError> {
+        // 写入的内容先落在临时文件本体上，覆盖写入中途失败时旧的内容还在
+        let result = blob.create_blob(&CreateBlobParams { id, truncate })?;
+        let mut file = BlobFile {
+            blob,
+            kv_file: Some(kv_file),
+            id,
+            size: result.size,
+            modified_time: SystemTime::now(),
+            offset: 0,
+            temp: Some(result.temp),
+        };
+        if append {
+            file.offset = file.size;
+        }
+        Ok(file)
+    }
+
+    // flush 之后再写时，从刚提交的内容开始一个新的临时文件本体
+    fn temp_blob(&mut self) -> Result<String, BlobStorageError> {
+        if let Some(temp) = &self.temp {
+            return Ok(temp.clone());
+        }
+        let params = CreateBlobParams {
+            id: self.id,
+            truncate: false,
+        };
+        let temp = self.blob.create_blob(&params)?.temp;
+        self.temp = Some(temp.clone());
+        Ok(temp)
+    }
+}
+
+impl DavMetaData for BlobFile {
+    fn len(&self) -> u64 {
+        self.size
+    }
+
+    fn modified(&self) -> webdav_handler::fs::FsResult<SystemTime> {
+        Ok(self.modified_time)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+}
+
+impl DavFile for BlobFile {
+    fn metadata<'a>(&'a mut self) -> webdav_handler::fs::FsFuture<Box<dyn DavMetaData>> {
+        async move { Ok(Box::new(self.clone()) as Box<dyn DavMetaData>) }.boxed()
+    }
+
+    fn write_buf<'a>(
+        &'a mut self,
+        mut buf: Box<dyn bytes::Buf + Send>,
+    ) -> webdav_handler::fs::FsFuture<()> {
+        let body = buf.copy_to_bytes(buf.remaining());
+        self.write_bytes(body)
+    }
+
+    fn write_bytes<'a>(&'a mut self, buf: Bytes) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            if self.kv_file.is_none() {
+                return Err(FsError::Forbidden);
+            }
+            let size = buf.len() as u64;
+            let params = WriteBlobParams {
+                temp: self.temp_blob()?,
+                offset: self.offset,
+                body: buf,
+            };
+            self.blob.write_blob(&params)?;
+            self.offset += size;
+            self.size = max(self.size, self.offset);
+            self.modified_time = SystemTime::now();
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn read_bytes<'a>(&'a mut self, count: usize) -> webdav_handler::fs::FsFuture<Bytes> {
+        async move {
+            // 写模式下还没提交的内容读不到，和只读模式下不能写对应
+            if self.kv_file.is_some() {
+                return Err(FsError::Forbidden);
+            }
+            let size = min(count as u64, self.size.saturating_sub(self.offset)) as usize;
+            if size == 0 {
+                return Ok(Bytes::new());
+            }
+            let params = ReadBlobParams {
+                id: self.id,
+                offset: self.offset,
+                size,
+            };
+            let result = self.blob.read_blob(&params)?;
+            self.offset += result.body.len() as u64;
+            Ok(result.body)
+        }
+        .boxed()
+    }
+
+    fn seek<'a>(&'a mut self, pos: SeekFrom) -> webdav_handler::fs::FsFuture<u64> {
+        async move {
+            let offset = match pos {
+                SeekFrom::Start(v) => v as i64,
+                SeekFrom::End(v) => self.size as i64 + v,
+                SeekFrom::Current(v) => self.offset as i64 + v,
+            };
+            if offset < 0 {
+                return Err(FsError::GeneralFailure);
+            }
+            self.offset = offset as u64;
+            Ok(self.offset)
+        }
+        .boxed()
+    }
+
+    fn flush<'a>(&'a mut self) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            let kv_file = match &self.kv_file {
+                Some(v) => v,
+                None => return Ok(()),
+            };
+            if let Some(temp) = &self.temp {
+                let params = CommitBlobParams {
+                    id: self.id,
+                    temp: temp.clone(),
+                };
+                self.blob.commit_blob(&params)?;
+                self.temp = None;
+            }
+            // 替换成功之后才更新大小，失败时标签和旧的内容保持一致
+            let params = SetLabelParams {
+                id: self.id,
+                label: [
+                    (String::from(BODY_SIZE), self.size.to_string()),
+                    (String::from(MODIFIED_TIME), format_time(self.modified_time)),
+                ]
+                .into_iter()
+                .collect(),
+            };
+            kv_file.set_label(&params)?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::adapter::storage::{
+        AddFileParams, CreateBlobResult, ListFileParams, MemFileKVFileStorage, MockBlobStorage,
+        ReadBlobResult, StatBlobResult, WriteBlobResult, KV,
+    };
+
+    const BODY: &[u8] = b"hello";
+
+    fn mock_blob() -> Arc<dyn BlobStorage> {
+        let mut blob = MockBlobStorage::new();
+        blob.expect_stat_blob().returning(|_| {
+            Ok(StatBlobResult {
+                size: BODY.len() as u64,
+                modified_time: SystemTime::UNIX_EPOCH,
+            })
+        });
+        blob.expect_read_blob().returning(|p| {
+            let start = p.offset as usize;
+            Ok(ReadBlobResult {
+                body: Bytes::from_static(&BODY[start..start + p.size]),
+            })
+        });
+        Arc::new(blob)
+    }
+
+    #[test]
+    fn test_read_and_seek() {
+        let mut file = BlobFile::open(mock_blob(), 1).unwrap();
+        assert_eq!(file.len(), 5);
+        assert_eq!(block_on(file.read_bytes(3)).unwrap(), Bytes::from_static(b"hel"));
+        assert_eq!(block_on(file.read_bytes(10)).unwrap(), Bytes::from_static(b"lo"));
+        assert!(block_on(file.read_bytes(10)).unwrap().is_empty());
+        assert_eq!(block_on(file.seek(SeekFrom::Start(1))).unwrap(), 1);
+        assert_eq!(block_on(file.read_bytes(10)).unwrap(), Bytes::from_static(b"ello"));
+        assert_eq!(block_on(file.seek(SeekFrom::End(-2))).unwrap(), 3);
+        assert!(block_on(file.seek(SeekFrom::Current(-4))).is_err());
+    }
+    #[test]
+    fn test_flush_after_commit() {
+        let mut blob = MockBlobStorage::new();
+        blob.expect_create_blob().returning(|_| {
+            Ok(CreateBlobResult {
+                temp: String::from("1.tmp"),
+                size: 0,
+            })
+        });
+        blob.expect_write_blob()
+            .returning(|p| Ok(WriteBlobResult { size: p.body.len() as u64 }));
+        blob.expect_commit_blob()
+            .returning(|_| Err(BlobStorageError::Backend(String::from("disk full"))));
+        let kv_file = Arc::new(MemFileKVFileStorage::new());
+        let id = kv_file.add_file(&AddFileParams { label: vec![] }).unwrap().id;
+        let mut file = BlobFile::create(Arc::new(blob), kv_file.clone(), id, true, false).unwrap();
+        block_on(file.write_bytes(Bytes::from_static(BODY))).unwrap();
+        assert!(block_on(file.read_bytes(1)).is_err());
+        // 替换失败时不更新大小
+        assert!(block_on(file.flush()).is_err());
+        let params = ListFileParams {
+            ids: vec![id],
+            selectors: vec![],
+            filter: None,
+        };
+        let files = kv_file.list_file(&params).unwrap().files;
+        assert!(KV::find_value(&files[0].label, &String::from(BODY_SIZE)).is_none());
+    }
+}
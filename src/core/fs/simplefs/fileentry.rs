@@ -0,0 +1,64 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::FutureExt;
+use webdav_handler::fs::{DavDirEntry, DavMetaData};
+
+use crate::adapter::storage::{KVFile, KV};
+use crate::core::fs::*;
+
+// 文件列表中的一项，大小和修改时间都来自文件的标签
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    name: String,
+    size: u64,
+    modified_time: SystemTime,
+}
+
+impl From<&KVFile> for FileEntry {
+    fn from(value: &KVFile) -> Self {
+        let size = KV::find_value(&value.label, &String::from(BODY_SIZE))
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(0);
+        // 还没有上传过内容的文件没有修改时间
+        let modified_time = KV::find_value(&value.label, &String::from(MODIFIED_TIME))
+            .and_then(|v| parse_time(&v))
+            .unwrap_or(UNIX_EPOCH);
+        FileEntry {
+            name: KV::find_value_default(&value.label, &String::from(TITLE), String::from("untitiled")),
+            size,
+            modified_time,
+        }
+    }
+}
+
+impl FileEntry {
+    // 同一个列表里标题重复时，用带编号的名字区分
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+}
+
+impl DavDirEntry for FileEntry {
+    fn name(&self) -> Vec<u8> {
+        self.name.to_string().into_bytes()
+    }
+
+    fn metadata<'a>(&'a self) -> webdav_handler::fs::FsFuture<Box<dyn DavMetaData>> {
+        async { Ok(Box::new(self.clone()) as Box<dyn DavMetaData>) }.boxed()
+    }
+}
+
+impl DavMetaData for FileEntry {
+    fn len(&self) -> u64 {
+        self.size
+    }
+
+    fn modified(&self) -> webdav_handler::fs::FsResult<SystemTime> {
+        Ok(self.modified_time)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,138 @@
+use std::cmp::min;
+use std::io::SeekFrom;
+use std::time::SystemTime;
+
+use bytes::{Buf, Bytes};
+use futures::FutureExt;
+use webdav_handler::fs::{DavFile, DavMetaData, FsError};
+
+use super::simplefs::SimpleFileSystem;
+
+// 文件目录下可写的标签文件，每行是标签的一个值，flush时写回到标签上
+#[derive(Debug, Clone)]
+pub struct LabelFile {
+    fs: SimpleFileSystem,
+    id: u64,
+    key: String,
+    body: Vec<u8>,
+    offset: usize,
+    modified_time: SystemTime,
+    dirty: bool,
+}
+
+impl LabelFile {
+    pub fn new(
+        fs: SimpleFileSystem,
+        id: u64,
+        key: String,
+        values: Vec<String>,
+        truncate: bool,
+        append: bool,
+    ) -> Self {
+        let body = if truncate {
+            vec![]
+        } else {
+            values.join("\n").into_bytes()
+        };
+        let offset = if append { body.len() } else { 0 };
+        LabelFile {
+            fs,
+            id,
+            key,
+            body,
+            offset,
+            modified_time: SystemTime::now(),
+            dirty: truncate,
+        }
+    }
+}
+
+impl DavMetaData for LabelFile {
+    fn len(&self) -> u64 {
+        self.body.len() as u64
+    }
+
+    fn modified(&self) -> webdav_handler::fs::FsResult<SystemTime> {
+        Ok(self.modified_time)
+    }
+
+    fn is_dir(&self) -> bool {
+        false
+    }
+}
+
+impl DavFile for LabelFile {
+    fn metadata<'a>(&'a mut self) -> webdav_handler::fs::FsFuture<Box<dyn DavMetaData>> {
+        async move { Ok(Box::new(self.clone()) as Box<dyn DavMetaData>) }.boxed()
+    }
+
+    fn write_buf<'a>(
+        &'a mut self,
+        mut buf: Box<dyn bytes::Buf + Send>,
+    ) -> webdav_handler::fs::FsFuture<()> {
+        let body = buf.copy_to_bytes(buf.remaining());
+        self.write_bytes(body)
+    }
+
+    fn write_bytes<'a>(&'a mut self, buf: Bytes) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            let end = self.offset + buf.len();
+            if self.body.len() < end {
+                self.body.resize(end, 0);
+            }
+            self.body[self.offset..end].copy_from_slice(&buf);
+            self.offset = end;
+            self.modified_time = SystemTime::now();
+            self.dirty = true;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn read_bytes<'a>(&'a mut self, count: usize) -> webdav_handler::fs::FsFuture<Bytes> {
+        async move {
+            let start = min(self.offset, self.body.len());
+            let end = min(start + count, self.body.len());
+            self.offset = end;
+            Ok(Bytes::copy_from_slice(&self.body[start..end]))
+        }
+        .boxed()
+    }
+
+    fn seek<'a>(&'a mut self, pos: SeekFrom) -> webdav_handler::fs::FsFuture<u64> {
+        async move {
+            let offset = match pos {
+                SeekFrom::Start(v) => v as i64,
+                SeekFrom::End(v) => self.body.len() as i64 + v,
+                SeekFrom::Current(v) => self.offset as i64 + v,
+            };
+            if offset < 0 {
+                return Err(FsError::GeneralFailure);
+            }
+            self.offset = offset as usize;
+            Ok(self.offset as u64)
+        }
+        .boxed()
+    }
+
+    fn flush<'a>(&'a mut self) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            if !self.dirty {
+                return Ok(());
+            }
+            let text = match std::str::from_utf8(&self.body) {
+                Ok(v) => v,
+                Err(_) => return Err(FsError::Forbidden),
+            };
+            let values = text
+                .lines()
+                .map(|l| l.trim().to_string())
+                .filter(|l| !l.is_empty())
+                .collect();
+            self.fs.update_file_label(self.id, self.key.clone(), values)?;
+            self.dirty = false;
+            Ok(())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,118 @@
+use webdav_handler::fs::DavProp;
+use xmltree::{Element, XMLNode};
+
+use crate::core::fs::{LABEL_NAMESPACE, LABEL_PREFIX};
+
+// 多值标签的每个值都放在一个子元素里
+const VALUE_NAME: &str = "value";
+
+// 属性名就是 XML 的元素名，只有合法的 NCName 才能作为属性暴露出去
+pub fn is_valid_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+// 标签以 soapdav 命名空间下的属性的形式暴露出去，单值直接作为文本，多值使用子元素；
+// key 不能作为元素名的标签不暴露，避免整个 PROPFIND 的响应变成不合法的 XML
+pub fn to_prop(key: &String, values: &Vec<String>) -> Option<DavProp> {
+    if !is_valid_name(key) {
+        return None;
+    }
+    let body = match values.len() {
+        1 => htmlescape::encode_minimal(&values[0]),
+        _ => values
+            .iter()
+            .map(|v| {
+                format!(
+                    "<{p}:{n}>{v}</{p}:{n}>",
+                    p = LABEL_PREFIX,
+                    n = VALUE_NAME,
+                    v = htmlescape::encode_minimal(v)
+                )
+            })
+            .collect(),
+    };
+    let xml = format!(
+        "<{p}:{k} xmlns:{p}=\"{ns}\">{b}</{p}:{k}>",
+        p = LABEL_PREFIX,
+        k = key,
+        ns = LABEL_NAMESPACE,
+        b = body
+    );
+    Some(DavProp {
+        name: key.clone(),
+        prefix: Some(String::from(LABEL_PREFIX)),
+        namespace: Some(String::from(LABEL_NAMESPACE)),
+        xml: Some(xml.into_bytes()),
+    })
+}
+
+pub fn is_label_prop(prop: &DavProp) -> bool {
+    match &prop.namespace {
+        Some(ns) => ns.as_str() == LABEL_NAMESPACE,
+        None => false,
+    }
+}
+
+// 解析 PROPPATCH 中设置的值，格式与 to_prop 输出的一致
+pub fn to_values(prop: &DavProp) -> Option<Vec<String>> {
+    let xml = match &prop.xml {
+        Some(v) => v,
+        None => return None,
+    };
+    let elem = match Element::parse(xml.as_slice()) {
+        Ok(v) => v,
+        Err(_) => return None,
+    };
+    let children: Vec<&Element> = elem
+        .children
+        .iter()
+        .filter_map(|n| match n {
+            XMLNode::Element(e) if e.name == VALUE_NAME => Some(e),
+            _ => None,
+        })
+        .collect();
+    if children.is_empty() {
+        return elem.get_text().map(|t| vec![t.trim().to_string()]);
+    }
+    Some(
+        children
+            .iter()
+            .filter_map(|e| e.get_text().map(|t| t.trim().to_string()))
+            .collect(),
+    )
+}
+
+// 返回给客户端的结果里只需要属性名
+pub fn to_name(prop: DavProp) -> DavProp {
+    DavProp {
+        name: prop.name,
+        prefix: prop.prefix,
+        namespace: prop.namespace,
+        xml: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prop_round_trip() {
+        let values = vec![String::from("a<b"), String::from("c")];
+        let prop = to_prop(&String::from("author"), &values).unwrap();
+        assert!(is_label_prop(&prop));
+        assert_eq!(to_values(&prop), Some(values));
+        let prop = to_prop(&String::from("volume"), &vec![String::from("3")]).unwrap();
+        assert_eq!(to_values(&prop), Some(vec![String::from("3")]));
+        assert!(to_name(prop).xml.is_none());
+        // 不能作为元素名的key不暴露
+        for key in ["", "1st", "a b", "a:b", "a<b"] {
+            assert!(to_prop(&key.to_string(), &vec![String::from("x")]).is_none(), "{}", key);
+        }
+    }
+}
@@ -1,6 +1,11 @@
 mod simplefs;
 mod staticdir;
 mod staticfile;
+mod blobfile;
+mod fileentry;
+mod nametemplate;
+mod labelfile;
+mod labelprop;
 mod collection_set;
 
-pub use simplefs::*;
\ No newline at end of file
+pub use simplefs::*;
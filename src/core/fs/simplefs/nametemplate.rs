@@ -0,0 +1,128 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+};
+
+use handlebars::{no_escape, Handlebars, TemplateError};
+use lazy_static::lazy_static;
+use lru::LruCache;
+use serde_json::{Map, Value};
+
+use crate::adapter::storage::{KVFile, KV};
+use crate::core::fs::*;
+
+const TEMPLATE_NAME: &str = "name";
+
+lazy_static! {
+    // 每次查找文件都要用到模板，编译结果缓存起来
+    static ref TEMPLATE_CACHE: Mutex<LruCache<String, Arc<NameTemplate>>> =
+        Mutex::new(LruCache::new(NonZeroUsize::new(64).unwrap()));
+    // (模板, 文件id, 标签摘要) -> 渲染出来的名字，标签没变的文件不用重新渲染
+    static ref NAME_CACHE: Mutex<LruCache<(u64, u64, u64), String>> =
+        Mutex::new(LruCache::new(NonZeroUsize::new(65536).unwrap()));
+}
+
+// 按筛选器组的模板渲染文件在列表中的名字
+pub struct NameTemplate {
+    registry: Handlebars<'static>,
+    digest: u64,
+}
+
+fn digest<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl NameTemplate {
+    pub fn new(template: &String) -> Result<Self, TemplateError> {
+        let mut registry = Handlebars::new();
+        // 渲染的是文件名，不是html
+        registry.register_escape_fn(no_escape);
+        registry.register_template_string(TEMPLATE_NAME, template)?;
+        Ok(NameTemplate {
+            registry,
+            digest: digest(template),
+        })
+    }
+
+    pub fn cached(template: &String) -> Result<Arc<Self>, TemplateError> {
+        if let Some(t) = TEMPLATE_CACHE.lock().unwrap().get(template) {
+            return Ok(t.clone());
+        }
+        let t = Arc::new(NameTemplate::new(template)?);
+        TEMPLATE_CACHE.lock().unwrap().put(template.clone(), t.clone());
+        Ok(t)
+    }
+
+    // 标签的摘要作为文件的版本，标签改过之后重新渲染
+    pub fn render_cached(&self, file: &KVFile) -> String {
+        let labels: Vec<(&String, &String)> = file.label.iter().map(|kv| (&kv.key, &kv.value)).collect();
+        let key = (self.digest, file.id, digest(&labels));
+        if let Some(name) = NAME_CACHE.lock().unwrap().get(&key) {
+            return name.clone();
+        }
+        let name = self.render(file);
+        NAME_CACHE.lock().unwrap().put(key, name.clone());
+        name
+    }
+
+    // 多值标签用逗号连起来，模板里引用了文件上没有的key时渲染为空
+    pub fn render(&self, file: &KVFile) -> String {
+        let mut data = Map::new();
+        for (key, values) in KV::to_labels(&file.label) {
+            data.insert(key, Value::String(values.join(", ")));
+        }
+        data.insert(String::from("id"), Value::from(file.id));
+        let title = KV::find_value_default(&file.label, &String::from(TITLE), String::from("untitiled"));
+        match self.registry.render(TEMPLATE_NAME, &data) {
+            // 名字会作为路径的一段，不能带有 '/'
+            Ok(name) if !name.trim().is_empty() => name.trim().replace('/', "_"),
+            _ => title,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(pairs: &[(&str, &str)]) -> KVFile {
+        KVFile {
+            id: 7,
+            label: pairs
+                .iter()
+                .map(|(k, v)| KV::new(k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template = NameTemplate::new(&String::from("{{series}} v{{volume}} - {{title}}")).unwrap();
+        let f = file(&[("series", "One/Piece"), ("volume", "3"), ("title", "Tom & Jerry")]);
+        assert_eq!(template.render(&f), "One_Piece v3 - Tom & Jerry");
+        let template = NameTemplate::new(&String::from("{{author}} #{{id}}")).unwrap();
+        assert_eq!(template.render(&file(&[("author", "a"), ("author", "b")])), "a, b #7");
+        // 渲染结果为空时退回到标题
+        let template = NameTemplate::new(&String::from("{{missing}}")).unwrap();
+        assert_eq!(template.render(&file(&[("title", "One Piece")])), "One Piece");
+        assert!(NameTemplate::new(&String::from("{{#if}}")).is_err());
+    }
+
+    #[test]
+    fn test_render_cached() {
+        let template = NameTemplate::cached(&String::from("{{series}} v{{volume}}")).unwrap();
+        let f = file(&[("series", "Vinland"), ("volume", "1")]);
+        assert_eq!(template.render_cached(&f), "Vinland v1");
+        assert_eq!(template.render_cached(&f), "Vinland v1");
+        // 标签改过之后名字跟着变
+        let f = file(&[("series", "Vinland"), ("volume", "2")]);
+        assert_eq!(template.render_cached(&f), "Vinland v2");
+        // 同样的文件换个模板不会拿到别的模板的结果
+        let template = NameTemplate::cached(&String::from("{{volume}} {{series}}")).unwrap();
+        assert_eq!(template.render_cached(&f), "2 Vinland");
+    }
+}
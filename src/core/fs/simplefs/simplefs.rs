@@ -1,32 +1,55 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use futures::future::BoxFuture;
 use futures::stream::iter;
 use futures::FutureExt;
+use http::StatusCode;
 use log::info;
 use percent_encoding::percent_decode;
 use webdav_handler::davpath::DavPath;
 use webdav_handler::fs::{
-    DavDirEntry, DavFile, DavFileSystem, DavMetaData, FsError, FsResult, FsStream, ReadDirMeta,
+    DavDirEntry, DavFile, DavFileSystem, DavMetaData, DavProp, FsError, FsResult, FsStream,
+    OpenOptions, ReadDirMeta,
 };
 
 use crate::adapter::storage::{
-    AddFileParams, DefineSelectorSetParams, KVFileStorage, ListFileParams, ListSelectorSetParams,
-    RemoveSelectorSetParams, Selector, SelectorSet, SelectorSetStorage, SelectorStorage, KV,
+    natural_cmp, parse_query, AddFileParams, AddLabelValueParams, BlobStorage, BlobStorageError, DefineSelectorSetParams,
+    KVFile, KVFileStorage, KVs, ListFileParams, ListSelectorSetParams,
+    RemoveBlobParams, RemoveFileParams, RemoveLabelValueParams, ReplaceLabelValueParams, RemoveSelectorSetParams, Selector,
+    SelectorExpr, SelectorOp, SelectorSet, SelectorSetStorage, SelectorSetStorageError, SelectorStorage, Selectors, SetLabelParams, SortSpec,
+    KV,
 };
 use crate::{AddFileResult, DefineSelectorResult, FilesystemError};
 use CollectionFS;
 
+use super::blobfile::BlobFile;
+use super::fileentry::FileEntry;
+use super::nametemplate::NameTemplate;
+use super::labelfile::LabelFile;
+use super::labelprop;
 use super::staticdir::StaticDir;
 use super::staticfile::StaticFile;
 use crate::core::fs::*;
 
+// 路径最终指向的是文件本体、文件旁边的标签目录，还是标签目录下的某个标签文件
+enum FileTarget {
+    Body,
+    LabelDir,
+    Label(String),
+}
+
 #[derive(Debug, Clone)]
 pub struct SimpleFileSystem {
     pub selector_set_storage: Arc<dyn SelectorSetStorage>,
     pub selector_storage: Arc<dyn SelectorStorage>,
     pub kv_file: Arc<dyn KVFileStorage>,
+    pub blob: Arc<dyn BlobStorage>,
+    // 回收站中的文件超过这个时间后会被彻底删除，为空时不自动清理
+    pub trash_retention: Option<Duration>,
+    // 筛选目录的名字后面带上文件数量，例如 "oda (12)"
+    pub value_counts: bool,
     // 这里需要根据实际情况定义 CollectionFileSystem 的字段
 }
 
@@ -35,24 +58,58 @@ impl SimpleFileSystem {
         selector_set_storage: Arc<dyn SelectorSetStorage>,
         selector_storage: Arc<dyn SelectorStorage>,
         kv_file: Arc<dyn KVFileStorage>,
+        blob: Arc<dyn BlobStorage>,
     ) -> Self {
         SimpleFileSystem {
             selector_set_storage,
             selector_storage,
             kv_file,
+            blob,
+            trash_retention: None,
+            value_counts: false,
         }
     }
 
+    pub fn with_trash_retention(mut self, retention: Duration) -> Self {
+        self.trash_retention = Some(retention);
+        self
+    }
+
+    pub fn with_value_counts(mut self, enabled: bool) -> Self {
+        self.value_counts = enabled;
+        self
+    }
+
+    // 彻底删除回收站中超过保留时间的文件，返回删除的数量
+    pub fn purge_expired_trash(&self) -> Result<usize, FilesystemError> {
+        let retention = match self.trash_retention {
+            Some(v) => v,
+            None => return Ok(0),
+        };
+        let now = SystemTime::now();
+        let ids = self
+            .list_file_by_selectors(vec![], true)?
+            .iter()
+            .filter(|f| {
+                match KV::find_value(&f.label, &String::from(TRASHED_TIME))
+                    .and_then(|v| parse_time(&v))
+                {
+                    Some(t) => now.duration_since(t).map_or(false, |d| d >= retention),
+                    None => false,
+                }
+            })
+            .map(|f| f.id)
+            .collect();
+        self.purge_files(ids)
+    }
+
+    // 先切分再解码，这样路径中编码过的 '/' 不会被当成分隔符
     fn split_path(path: &DavPath) -> Result<Vec<String>, std::str::Utf8Error> {
-        match percent_decode(path.as_bytes()).decode_utf8() {
-            Ok(cs) => Ok(cs
-                .into_owned()
-                .split('/')
-                .map(|s| s.to_string())
-                .filter(|x| !x.is_empty())
-                .collect()),
-            Err(e) => Err(e),
-        }
+        path.as_bytes()
+            .split(|c| *c == b'/')
+            .filter(|x| !x.is_empty())
+            .map(|s| percent_decode(s).decode_utf8().map(|cs| cs.into_owned()))
+            .collect()
     }
 
     fn read_dir_stream<'a>(
@@ -66,59 +123,1232 @@ impl SimpleFileSystem {
         if tokens.is_empty() {
             return self.read_root_dir_stream(meta);
         }
-        // 构造筛选器组
-        let mut selector_set = match self
+        // 回收站
+        if SimpleFileSystem::is_trash(tokens.front()) {
+            if tokens.len() == 1 {
+                return self.read_trash_dir_stream(meta);
+            }
+            return self.read_label_dir_stream(paths, meta);
+        }
+        // 临时查询
+        if SimpleFileSystem::is_query(tokens.front()) {
+            match tokens.len() {
+                1 => return Ok(Box::pin(iter(vec![]))),
+                2 => return self.read_query_dir_stream(&tokens[1], meta),
+                _ => return self.read_label_dir_stream(paths, meta),
+            }
+        }
+        // 筛选器组的分组目录
+        let group = self.list_collection_group(paths)?;
+        if !group.is_empty() {
+            let dirs: Vec<Box<dyn DavDirEntry>> = group
+                .into_iter()
+                .map(|x| Box::new(x) as Box<dyn DavDirEntry>)
+                .collect();
+            return Ok(Box::pin(iter(dirs)));
+        }
+        let selector_set = self.fill_selector_set(&mut tokens)?;
+        // 筛选器还没有满，找到下一个筛选项，并将可选结果以目录的形式返回
+        if !selector_set.is_full() {
+            info!("return next filter");
+            return self.read_selecting_dir_stream(selector_set, meta);
+        }
+        // 参数量刚好填满筛选器，说明需要返回文件列表
+        if tokens.is_empty() {
+            info!("return file list");
+            return self.read_matching_dir_stream(selector_set, meta);
+        }
+        // 进入到下一层，只有文件旁边的标签目录可以列出内容
+        info!("return file meta");
+        self.read_label_dir_stream(paths, meta)
+    }
+
+    // 和 read_dir_stream 一样解析路径，返回路径对应的目录或文件的信息
+    fn read_metadata(&self, paths: &Vec<String>) -> FsResult<Box<dyn DavMetaData>> {
+        let mut tokens = VecDeque::from(paths.clone());
+        let name = match tokens.back() {
+            Some(v) => v.clone(),
+            None => return Ok(Box::new(StaticDir::from(&String::from("root")))),
+        };
+        if SimpleFileSystem::is_trash(tokens.front()) {
+            if tokens.len() == 1 {
+                return Ok(Box::new(StaticDir::from(&name)));
+            }
+            return self.read_file_metadata(paths);
+        }
+        if SimpleFileSystem::is_query(tokens.front()) {
+            match tokens.len() {
+                1 => return Ok(Box::new(StaticDir::from(&name))),
+                2 => {
+                    SimpleFileSystem::parse_query_dir(&name)?;
+                    return Ok(Box::new(StaticDir::from(&name)));
+                }
+                _ => return self.read_file_metadata(paths),
+            }
+        }
+        // 分组目录的修改时间取里面最新的子目录
+        let group = self.list_collection_group(paths)?;
+        if let Some(time) = group.iter().filter_map(|d| d.modified().ok()).max() {
+            return Ok(Box::new(StaticDir::new(&name, time)));
+        }
+        let selector_set = self.fill_selector_set(&mut tokens)?;
+        // 筛选器组本身、还没填满的筛选器以及填满之后的文件列表都是目录
+        if tokens.is_empty() {
+            let modified_time = SimpleFileSystem::selector_set_time(&selector_set);
+            return Ok(Box::new(StaticDir::new(&name, modified_time)));
+        }
+        self.read_file_metadata(paths)
+    }
+
+    // 文件本体的大小和修改时间来自标签，标签目录下的标签文件取自己的内容
+    fn read_file_metadata(&self, paths: &Vec<String>) -> FsResult<Box<dyn DavMetaData>> {
+        let (file, target) = self.resolve_file(paths)?;
+        match target {
+            FileTarget::Body => Ok(Box::new(FileEntry::from(&file))),
+            FileTarget::LabelDir => Ok(Box::new(SimpleFileSystem::label_dir(&file, paths))),
+            FileTarget::Label(name) => SimpleFileSystem::find_label_file(&file, &name)
+                .map(|f| Box::new(f) as Box<dyn DavMetaData>),
+        }
+    }
+
+    // 标签目录的修改时间跟文件本体一致
+    fn label_dir(file: &KVFile, paths: &Vec<String>) -> StaticDir {
+        let name = paths.last().cloned().unwrap_or_default();
+        StaticDir::new(&name, FileEntry::from(file).modified().unwrap_or(UNIX_EPOCH))
+    }
+
+    // 标签文件可以是 key=value，也可以是 key，后者的内容是 key 的所有值
+    fn find_label_file(file: &KVFile, name: &String) -> FsResult<StaticFile> {
+        if name.contains('=') {
+            return file
+                .label
+                .iter()
+                .map(StaticFile::from)
+                .find(|f| f.name() == name.as_bytes())
+                .ok_or(FsError::NotFound);
+        }
+        let values = KV::find_values(&file.label, name);
+        if values.is_empty() {
+            return Err(FsError::NotFound);
+        }
+        Ok(StaticFile::from_values(name, &values))
+    }
+
+    fn selector_set_time(selector_set: &SelectorSet) -> SystemTime {
+        selector_set.modified_time.unwrap_or(UNIX_EPOCH)
+    }
+
+    fn is_trash(token: Option<&String>) -> bool {
+        match token {
+            Some(t) => t.as_str() == TRASH_DIR,
+            None => false,
+        }
+    }
+
+    fn is_query(token: Option<&String>) -> bool {
+        match token {
+            Some(t) => t.as_str() == QUERY_DIR,
+            None => false,
+        }
+    }
+
+    // 查询写得不对时当作目录不存在
+    fn parse_query_dir(query: &String) -> FsResult<SelectorExpr> {
+        parse_query(query).map_err(|e| {
+            info!("invalid query {:?}: {}", query, e);
+            FsError::NotFound
+        })
+    }
+
+    fn value_dir_name(&self, value: &str, count: usize) -> String {
+        match self.value_counts {
+            true => format!("{} ({})", value, count),
+            false => value.to_string(),
+        }
+    }
+
+    // 带数量的目录名在解析时去掉数量，不带数量的路径也能访问；
+    // 值本身就是 "xxx (1)" 这种形式时按原样使用
+    fn strip_value_count(&self, key: &String, name: String) -> FsResult<String> {
+        if !self.value_counts {
+            return Ok(name);
+        }
+        let value = match name.strip_suffix(')').and_then(|s| s.rsplit_once(" (")) {
+            Some((value, count)) if !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()) => {
+                value.to_string()
+            }
+            _ => return Ok(name),
+        };
+        // 原样的名字是已有的值或者别名时按原样使用，否则去掉数量
+        match self.has_value(key, &name)? {
+            true => Ok(name),
+            false => Ok(value),
+        }
+    }
+
+    // 只查索引里有没有这个值，不列出key的所有值
+    fn has_value(&self, key: &String, value: &String) -> Result<bool, FilesystemError> {
+        if self.selector_storage.get_aliases_by_key(key)?.contains_key(value) {
+            return Ok(true);
+        }
+        let result = self.selector_storage.list_selector(&ListSelectorParams {
+            key: vec![key.clone()],
+            exclude_key: vec![],
+            value: vec![value.clone()],
+        })?;
+        Ok(result.selectors.iter().any(|s| s.value.contains(value)))
+    }
+
+    // 根目录下的保留名字不能用作筛选器组
+    // 一个筛选器组的名字也不能是另一个的前几段，否则没法区分路径上的是分组还是筛选的值
+    fn check_collection_name(&self, name: &String, current: Option<&String>) -> Result<(), FilesystemError> {
+        if name.split('/').any(|s| s.starts_with('.')) || name.split('/').next() == Some(QUERY_DIR) {
+            return Err(FilesystemError::Conflict(format!(
+                "collection name {} is reserved",
+                name
+            )));
+        }
+        let result = self
             .selector_set_storage
-            .get_selector_set_by_name(&tokens.pop_front().unwrap())
-        {
-            Ok(v) => v,
-            Err(_e) => return Err(FsError::NotFound),
+            .list_selector_set(&ListSelectorSetParams { names: vec![] })?;
+        for ss in &result.selector_set {
+            if Some(&ss.name) == current {
+                continue;
+            }
+            if ss.name.starts_with(&format!("{}/", name)) || name.starts_with(&format!("{}/", ss.name)) {
+                return Err(FilesystemError::Conflict(format!(
+                    "collection name {} conflicts with {}",
+                    name, ss.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // 名字带有 '/' 的筛选器组会被放到分组目录里，返回 prefix 这个分组下的子目录
+    fn list_collection_group(&self, prefix: &Vec<String>) -> Result<Vec<StaticDir>, FilesystemError> {
+        // 只取名字以 "prefix/" 开头的筛选器组，不用每次都扫描所有的
+        let name_prefix = match prefix.is_empty() {
+            true => String::new(),
+            false => format!("{}/", prefix.join("/")),
         };
+        let selector_sets = self
+            .selector_set_storage
+            .list_selector_set_by_prefix(&name_prefix)?;
+        // 分组目录的修改时间取里面最新的筛选器组
+        let mut children: BTreeMap<String, SystemTime> = BTreeMap::new();
+        for ss in &selector_sets {
+            let segments: Vec<&str> = ss.name.split('/').collect();
+            if segments.len() <= prefix.len() || segments.iter().zip(prefix.iter()).any(|(a, b)| a != b) {
+                continue;
+            }
+            let modified_time = SimpleFileSystem::selector_set_time(ss);
+            let time = children.entry(segments[prefix.len()].to_string()).or_insert(modified_time);
+            *time = (*time).max(modified_time);
+        }
+        let mut children: Vec<(String, SystemTime)> = children.into_iter().collect();
+        children.sort_by(|a, b| natural_cmp(&a.0, &b.0));
+        Ok(children
+            .iter()
+            .map(|(name, time)| StaticDir::new(name, *time))
+            .collect())
+    }
+
+    // 构造筛选器组，并将路径中的参数逐个填到selector中，剩余的路径留在tokens里
+    fn fill_selector_set(&self, tokens: &mut VecDeque<String>) -> FsResult<SelectorSet> {
+        // 名字带有 '/' 的筛选器组占用路径的前几段，名字之间不会互为前缀，所以找到的第一个就是
+        let mut found = None;
+        for n in 1..=tokens.len() {
+            let name = tokens.iter().take(n).cloned().collect::<Vec<String>>().join("/");
+            match self.selector_set_storage.get_selector_set_by_name(&name) {
+                Ok(v) => {
+                    tokens.drain(..n);
+                    found = Some(v);
+                    break;
+                }
+                Err(SelectorSetStorageError::NotFound) => continue,
+                Err(e) => return Err(FsError::from(e)),
+            }
+        }
+        let mut selector_set = found.ok_or(FsError::NotFound)?;
         info!("get selector_set");
-        // 将路径中的参数逐个填到selector中
         while !tokens.is_empty() && !selector_set.is_full() {
             let selector_value = tokens.pop_front().unwrap();
             // TODO: 带点的都是特殊说明文件，不是目录
             if selector_value.starts_with('.') {
                 return Err(FsError::NotFound);
+            }
+            let key = selector_set.get_next_required_selector().unwrap().get_key();
+            let selector_value = self.strip_value_count(&key, selector_value)?;
+            if selector_value.as_str() == UNSET_DIR {
+                selector_set.add_required_empty();
+                continue;
+            }
+            // 路径中使用别名时，和使用规范值是同一个目录
+            let aliases = self.selector_storage.get_aliases_by_key(&key)?;
+            let selector_value = aliases.get(&selector_value).cloned().unwrap_or(selector_value);
+            selector_set.add_required_value(selector_value);
+        }
+        Ok(selector_set)
+    }
+
+    fn list_file_by_selectors(
+        &self,
+        selectors: Selectors,
+        trashed: bool,
+    ) -> Result<Vec<KVFile>, FilesystemError> {
+        self.list_file_by_filter(selectors, None, trashed)
+    }
+
+    // 回收站里的文件对所有筛选器组都不可见，只能在回收站中找到
+    fn list_file_by_filter(
+        &self,
+        selectors: Selectors,
+        filter: Option<&SelectorExpr>,
+        trashed: bool,
+    ) -> Result<Vec<KVFile>, FilesystemError> {
+        let selectors = selectors
+            .iter()
+            .map(|s| self.expand_selector(s))
+            .collect::<Result<Selectors, FilesystemError>>()?;
+        let filter = match filter {
+            Some(f) => Some(self.expand_filter(f)?),
+            None => None,
+        };
+        let result = self.kv_file.list_file(&ListFileParams {
+            selectors,
+            ids: vec![],
+            filter,
+        })?;
+        let trashed_key = String::from(TRASHED_TIME);
+        Ok(result
+            .files
+            .into_iter()
+            .filter(|f| KV::find_value(&f.label, &trashed_key).is_some() == trashed)
+            .collect())
+    }
+
+    // 标题不支持别名，其他的key都要把别名加到筛选条件里
+    fn expand_selector(&self, selector: &Selector) -> Result<Selector, FilesystemError> {
+        if selector.key.as_str() == TITLE {
+            return Ok(selector.clone());
+        }
+        let aliases = self.selector_storage.get_aliases_by_key(&selector.key)?;
+        if aliases.is_empty() {
+            return Ok(selector.clone());
+        }
+        Ok(selector.with_aliases(&aliases))
+    }
+
+    fn expand_filter(&self, filter: &SelectorExpr) -> Result<SelectorExpr, FilesystemError> {
+        filter.try_map(&mut |s| self.expand_selector(s))
+    }
+
+    // 组合条件没法直接写成标签，只能检查写入之后的文件是否还满足
+    fn check_filter(&self, selector_set: &SelectorSet, kvs: &KVs) -> FsResult<()> {
+        let filter = match &selector_set.filter {
+            Some(f) => self.expand_filter(f)?,
+            None => return Ok(()),
+        };
+        if !filter.is_match(&KV::to_labels(kvs)) {
+            return Err(FsError::Forbidden);
+        }
+        Ok(())
+    }
+
+    // 列表中的文件名，默认是标题，同一个列表里重名的文件在名字后面加上 (#id)
+    fn file_names(files: &Vec<KVFile>, template: Option<&NameTemplate>) -> Vec<String> {
+        let names: Vec<String> = files
+            .iter()
+            .map(|file| match template {
+                Some(t) => t.render_cached(file),
+                None => KV::find_value_default(&file.label, &String::from(TITLE), String::from("untitiled")),
+            })
+            .collect();
+        let mut counts: HashMap<&String, usize> = HashMap::new();
+        for name in &names {
+            *counts.entry(name).or_default() += 1;
+        }
+        files
+            .iter()
+            .zip(names.iter())
+            .map(|(file, name)| match counts.get(name) {
+                Some(count) if *count > 1 => format!("{} (#{})", name, file.id),
+                _ => name.clone(),
+            })
+            .collect()
+    }
+
+    // 先按筛选器组的排序规则，再按标题的自然顺序，最后按id，保证每次列出的顺序一致
+    fn sort_files(files: &mut Vec<KVFile>, specs: &Vec<SortSpec>) {
+        let title = SortSpec::new(TITLE, false);
+        files.sort_by(|a, b| {
+            specs
+                .iter()
+                .chain(std::iter::once(&title))
+                .map(|s| s.compare(a, b))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.id.cmp(&b.id))
+        });
+    }
+
+    fn file_entries(files: &Vec<KVFile>, template: Option<&NameTemplate>) -> Vec<FileEntry> {
+        files
+            .iter()
+            .zip(SimpleFileSystem::file_names(files, template))
+            .map(|(file, name)| FileEntry::from(file).with_name(name))
+            .collect()
+    }
+
+    // 文件列表中每个文件后面跟着它的标签目录
+    fn file_dir_entries(files: &Vec<KVFile>, template: Option<&NameTemplate>) -> Vec<Box<dyn DavDirEntry>> {
+        let mut entries: Vec<Box<dyn DavDirEntry>> = vec![];
+        for entry in SimpleFileSystem::file_entries(files, template) {
+            let name = format!("{}{}", String::from_utf8_lossy(&entry.name()), LABEL_DIR_SUFFIX);
+            let dir = StaticDir::new(&name, entry.modified().unwrap_or(UNIX_EPOCH));
+            entries.push(Box::new(entry));
+            entries.push(Box::new(dir));
+        }
+        entries
+    }
+
+    fn name_template(selector_set: &SelectorSet) -> Result<Option<Arc<NameTemplate>>, FilesystemError> {
+        match &selector_set.name_template {
+            Some(t) => NameTemplate::cached(t)
+                .map(Some)
+                .map_err(|e| FilesystemError::InvalidArgument(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    // 拆出文件名末尾的 (#id)，没有的话返回None
+    fn split_file_name(name: &String) -> Option<(String, u64)> {
+        let (title, id) = name.strip_suffix(')')?.rsplit_once(" (#")?;
+        Some((title.to_string(), id.parse::<u64>().ok()?))
+    }
+
+    // 在筛选条件下找到文件名对应的所有文件，文件名可能带有 (#id)
+    fn find_files(
+        &self,
+        selectors: &Selectors,
+        filter: Option<&SelectorExpr>,
+        name: &String,
+        trashed: bool,
+    ) -> Result<Vec<KVFile>, FilesystemError> {
+        let title_selectors = |title: String| {
+            let mut result = selectors.clone();
+            result.push(Selector::new(String::from(TITLE), vec![title]));
+            result
+        };
+        if let Some((title, id)) = SimpleFileSystem::split_file_name(name) {
+            let files = self.list_file_by_filter(title_selectors(title), filter, trashed)?;
+            if let Some(file) = files.into_iter().find(|f| f.id == id) {
+                return Ok(vec![file]);
+            }
+        }
+        // 标题本身也可能以 (#数字) 结尾，找不到编号对应的文件时按完整的标题找
+        self.list_file_by_filter(title_selectors(name.clone()), filter, trashed)
+    }
+
+    // 文件名必须唯一地对应一个文件，重名时只能通过带编号的名字访问，不带编号的名字返回 Exists
+    fn find_file(
+        &self,
+        selectors: &Selectors,
+        filter: Option<&SelectorExpr>,
+        name: &String,
+        trashed: bool,
+    ) -> FsResult<KVFile> {
+        SimpleFileSystem::only_file(self.find_files(selectors, filter, name, trashed)?)
+    }
+
+    fn only_file(files: Vec<KVFile>) -> FsResult<KVFile> {
+        match files.as_slice() {
+            [file] => Ok(file.clone()),
+            [] => Err(FsError::NotFound),
+            _ => Err(FsError::Exists),
+        }
+    }
+
+    // 筛选器组的所有条件加上文件名一起确定一个文件
+    fn find_files_in(&self, selector_set: &SelectorSet, name: &String) -> Result<Vec<KVFile>, FilesystemError> {
+        let selectors = SimpleFileSystem::selector_set_selectors(selector_set);
+        let filter = selector_set.filter.as_ref();
+        let template = match SimpleFileSystem::name_template(selector_set)? {
+            Some(t) => t,
+            None => return self.find_files(&selectors, filter, name, false),
+        };
+        // 模板渲染出来的名字没法反推出标签，只能跟目录下每个文件的名字比较，标签没变的文件直接用缓存的名字
+        let files = self.list_file_by_filter(selectors, filter, false)?;
+        let names = SimpleFileSystem::file_names(&files, Some(template.as_ref()));
+        Ok(files
+            .into_iter()
+            .zip(names)
+            .filter(|(_, n)| n == name)
+            .map(|(f, _)| f)
+            .collect())
+    }
+
+    fn find_file_in(&self, selector_set: &SelectorSet, name: &String) -> FsResult<KVFile> {
+        SimpleFileSystem::only_file(self.find_files_in(selector_set, name)?)
+    }
+
+    fn selector_set_selectors(selector_set: &SelectorSet) -> Selectors {
+        let mut selectors = selector_set.static_selectors.clone();
+        selectors.extend(selector_set.dynamic_selectors.iter().cloned());
+        selectors
+    }
+
+    // 同一个筛选器组的同一个目录下不允许出现两个同名的文件
+    fn check_title_free(&self, selector_set: &SelectorSet, title: &String, id: u64) -> Result<(), FilesystemError> {
+        let mut selectors = SimpleFileSystem::selector_set_selectors(selector_set);
+        selectors.push(Selector::new(String::from(TITLE), vec![title.clone()]));
+        if self
+            .list_file_by_filter(selectors, selector_set.filter.as_ref(), false)?
+            .iter()
+            .any(|f| f.id != id)
+        {
+            return Err(FilesystemError::DuplicateTitle(title.clone()));
+        }
+        Ok(())
+    }
+
+    // 找到路径对应的文件以及路径指向的是文件的哪一部分
+    fn resolve_file(&self, paths: &Vec<String>) -> FsResult<(KVFile, FileTarget)> {
+        let mut tokens = VecDeque::from(paths.clone());
+        let find: Box<dyn Fn(&String) -> FsResult<KVFile> + '_> =
+            if SimpleFileSystem::is_trash(tokens.front()) {
+                tokens.pop_front();
+                Box::new(|name: &String| self.find_file(&vec![], None, name, true))
+            } else if SimpleFileSystem::is_query(tokens.front()) {
+                tokens.pop_front();
+                let filter = match tokens.pop_front() {
+                    Some(query) => SimpleFileSystem::parse_query_dir(&query)?,
+                    None => return Err(FsError::NotFound),
+                };
+                Box::new(move |name: &String| self.find_file(&vec![], Some(&filter), name, false))
             } else {
-                selector_set.add_required_value(selector_value);
+                let selector_set = self.fill_selector_set(&mut tokens)?;
+                if !selector_set.is_full() {
+                    return Err(FsError::NotFound);
+                }
+                Box::new(move |name: &String| self.find_file_in(&selector_set, name))
+            };
+        let name = tokens.pop_front().ok_or(FsError::NotFound)?;
+        let label = tokens.pop_front();
+        if !tokens.is_empty() {
+            return Err(FsError::NotFound);
+        }
+        // 文件本体下面没有东西，名字本身以 .labels 结尾的文件优先当作文件本体
+        if label.is_none() {
+            match find(&name) {
+                Err(FsError::NotFound) => {}
+                result => return result.map(|f| (f, FileTarget::Body)),
+            }
+        }
+        let file = match name.strip_suffix(LABEL_DIR_SUFFIX) {
+            Some(n) if !n.is_empty() => find(&n.to_string())?,
+            _ => return Err(FsError::NotFound),
+        };
+        match label {
+            Some(l) => Ok((file, FileTarget::Label(l))),
+            None => Ok((file, FileTarget::LabelDir)),
+        }
+    }
+
+    // 打开文件本体，或者标签目录下的标签文件
+    fn open_file<'a>(&'a self, paths: &Vec<String>) -> FsResult<Box<dyn DavFile>> {
+        let (file, target) = self.resolve_file(paths)?;
+        match target {
+            FileTarget::Body => Ok(Box::new(BlobFile::open(self.blob.clone(), file.id)?)),
+            FileTarget::LabelDir => Ok(Box::new(SimpleFileSystem::label_dir(&file, paths))),
+            FileTarget::Label(name) => SimpleFileSystem::find_label_file(&file, &name)
+                .map(|f| Box::new(f) as Box<dyn DavFile>),
+        }
+    }
+
+    // 文件路径由筛选器组、填满筛选器的参数和文件名组成，文件只能位于筛选器填满之后的那一层
+    fn fill_file_path(&self, paths: &Vec<String>) -> FsResult<(SelectorSet, String)> {
+        let mut tokens = VecDeque::from(paths.clone());
+        let selector_set = self.fill_selector_set(&mut tokens)?;
+        if !selector_set.is_full() || tokens.len() != 1 {
+            return Err(FsError::Forbidden);
+        }
+        Ok((selector_set, tokens.pop_front().unwrap()))
+    }
+
+    // 以写模式打开标签目录下的标签文件，或者文件本体
+    fn open_writable<'a>(
+        &'a self,
+        paths: &Vec<String>,
+        options: &OpenOptions,
+    ) -> FsResult<Box<dyn DavFile>> {
+        match self.resolve_file(paths) {
+            Ok((file, FileTarget::Label(name))) => self.open_label_file(file, name, options),
+            Ok((_, FileTarget::LabelDir)) => Err(FsError::Forbidden),
+            Ok((_, FileTarget::Body)) => self.create_file(paths, options),
+            // 只有找不到文件时才新建，别的错误原样返回
+            Err(FsError::NotFound) if options.create || options.create_new => {
+                self.create_file(paths, options)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // 标签文件可以是 key 或者 key=value，写入的内容都会替换掉 key 的所有值
+    fn open_label_file(
+        &self,
+        file: KVFile,
+        name: String,
+        options: &OpenOptions,
+    ) -> FsResult<Box<dyn DavFile>> {
+        let key = match name.split_once('=') {
+            Some((k, _)) => k.to_string(),
+            None => name,
+        };
+        if key.is_empty() || BASIC_META_KEYS.contains(&key.as_str()) {
+            return Err(FsError::Forbidden);
+        }
+        let values = KV::find_values(&file.label, &key);
+        if values.is_empty() && !(options.create || options.create_new) {
+            return Err(FsError::NotFound);
+        }
+        Ok(Box::new(LabelFile::new(
+            self.clone(),
+            file.id,
+            key,
+            values,
+            options.truncate,
+            options.append,
+        )))
+    }
+
+    // 以写模式打开文件本体，文件不存在时根据路径中的参数和文件名新建一个KVFile
+    fn create_file<'a>(
+        &'a self,
+        paths: &Vec<String>,
+        options: &OpenOptions,
+    ) -> FsResult<Box<dyn DavFile>> {
+        let (selector_set, name) = self.fill_file_path(paths)?;
+        let (id, created) = match self.find_files_in(&selector_set, &name)?.as_slice() {
+            // 新文件的名字不能跟别的文件的标签目录混在一起
+            [] if name.ends_with(LABEL_DIR_SUFFIX) => return Err(FsError::Forbidden),
+            // 用模板渲染名字的目录没法从文件名反推出标题
+            [] if selector_set.name_template.is_some() => return Err(FsError::Forbidden),
+            [] if options.create || options.create_new => {
+                (self.add_file_by_selector_set(&selector_set, name)?, true)
+            }
+            [] => return Err(FsError::NotFound),
+            [file] if !options.create_new => (file.id, false),
+            // 重名的文件只能通过带编号的名字写入
+            _ => return Err(FsError::Exists),
+        };
+        match BlobFile::create(
+            self.blob.clone(),
+            self.kv_file.clone(),
+            id,
+            options.truncate,
+            options.append,
+        ) {
+            Ok(file) => Ok(Box::new(file)),
+            Err(e) => {
+                // 文件本体没建出来时，把刚加上的KVFile删掉，避免列表里出现没有内容的文件
+                if created {
+                    let _ = self.kv_file.remove_file(&RemoveFileParams { ids: vec![id] });
+                }
+                Err(FsError::from(e))
+            }
+        }
+    }
+
+    // 同一个筛选器组内的移动，等价于把路径参数和文件名上的差异改写到文件的标签上
+    fn rename_file(&self, from: &Vec<String>, to: &Vec<String>) -> FsResult<()> {
+        // 文件目录下标签文件的改名，等价于修改这个标签的值
+        if let Some(result) = self.rename_label_file(from, to) {
+            return result;
+        }
+        // 从回收站中移出去就是还原
+        if SimpleFileSystem::is_trash(from.first()) {
+            return self.restore_file_to(from, to);
+        }
+        let (from_selector_set, from_name) = self.fill_file_path(from)?;
+        let (to_selector_set, to_name) = self.fill_file_path(to)?;
+        // 跨筛选器组的移动没法用标签表达
+        if from_selector_set.name != to_selector_set.name {
+            return Err(FsError::IsRemote);
+        }
+        let file = self.find_file_in(&from_selector_set, &from_name)?;
+        let from_title = KV::find_value_default(&file.label, &String::from(TITLE), String::new());
+        // 文件名没变时，带编号的名字也不会改写到标题上
+        let to_title = if from_name == to_name {
+            from_title.clone()
+        } else if from_selector_set.name_template.is_some() {
+            // 模板渲染出来的名字没法反推出标题
+            return Err(FsError::Forbidden);
+        } else {
+            to_name
+        };
+        self.check_title_free(&to_selector_set, &to_title, file.id)?;
+        let mut removed = vec![];
+        let mut added = vec![];
+        for (from_selector, to_selector) in from_selector_set
+            .dynamic_selectors
+            .iter()
+            .zip(to_selector_set.dynamic_selectors.iter())
+        {
+            if from_selector.value != to_selector.value || from_selector.op != to_selector.op {
+                SimpleFileSystem::selector_value(from_selector)?;
+                // 文件上保存的可能是别名，所以别名也要一起删掉
+                for v in self.expand_selector(from_selector)?.value {
+                    removed.push(KV::new(from_selector.key.clone(), v));
+                }
+                // 移动到 _unset 时只删不加
+                if let Some(v) = SimpleFileSystem::selector_label(to_selector)? {
+                    added.push(KV::new(to_selector.key.clone(), v));
+                }
+            }
+        }
+        if from_title != to_title {
+            removed.push(KV::new(String::from(TITLE), from_title));
+            added.push(KV::new(String::from(TITLE), to_title));
+        }
+        // 只替换路径上对应的那个值，多值标签的其他值保持不变；删和加在同一次写入里完成
+        self.replace_file_label_value(file.id, removed, added)?;
+        Ok(())
+    }
+
+    // from 不是标签文件时返回 None，交给其他的移动逻辑处理
+    fn rename_label_file(&self, from: &Vec<String>, to: &Vec<String>) -> Option<FsResult<()>> {
+        let (file, from_name) = match self.resolve_file(from) {
+            Ok((f, FileTarget::Label(name))) => (f, name),
+            _ => return None,
+        };
+        // 标签文件只能在自己的标签目录下改名
+        let to_name = match self.resolve_file(to) {
+            Ok((f, FileTarget::Label(name))) if f.id == file.id => name,
+            _ => return Some(Err(FsError::Forbidden)),
+        };
+        let (from_key, from_value) = match from_name.split_once('=') {
+            Some(v) => v,
+            None => return Some(Err(FsError::NotFound)),
+        };
+        let (to_key, to_value) = match to_name.split_once('=') {
+            Some(v) => v,
+            None => return Some(Err(FsError::Forbidden)),
+        };
+        if from_key != to_key || to_value.is_empty() || BASIC_META_KEYS.contains(&from_key) {
+            return Some(Err(FsError::Forbidden));
+        }
+        let values = KV::find_values(&file.label, &from_key.to_string());
+        if !values.iter().any(|v| v.as_str() == from_value) {
+            return Some(Err(FsError::NotFound));
+        }
+        let values = values
+            .into_iter()
+            .map(|v| {
+                if v.as_str() == from_value {
+                    to_value.to_string()
+                } else {
+                    v
+                }
+            })
+            .collect();
+        Some(
+            self.set_file_label(file.id, from_key.to_string(), values)
+                .map_err(FsError::from),
+        )
+    }
+
+    // 复制不会产生新的文件本体，只是把目标路径对应的值追加到文件的标签上
+    fn copy_file(&self, from: &Vec<String>, to: &Vec<String>) -> FsResult<()> {
+        let (from_selector_set, from_name) = self.fill_file_path(from)?;
+        let (to_selector_set, to_name) = self.fill_file_path(to)?;
+        // 改名之后就是两个不同的文件了，没法用标签表达
+        if from_name != to_name {
+            return Err(FsError::Forbidden);
+        }
+        let file = self.find_file_in(&from_selector_set, &from_name)?;
+        let title = KV::find_value_default(&file.label, &String::from(TITLE), String::new());
+        self.check_title_free(&to_selector_set, &title, file.id)?;
+        self.add_selector_set_value(&file, &to_selector_set)
+    }
+
+    // 还原回收站中的文件，并把目标路径对应的值追加到文件的标签上
+    fn restore_file_to(&self, from: &Vec<String>, to: &Vec<String>) -> FsResult<()> {
+        let file = match self.resolve_file(from)? {
+            (file, FileTarget::Body) => file,
+            _ => return Err(FsError::Forbidden),
+        };
+        let (to_selector_set, to_name) = self.fill_file_path(to)?;
+        let title = KV::find_value_default(&file.label, &String::from(TITLE), String::new());
+        // 回收站里带编号的名字还原之后也可以保持不变
+        let from_name = from.last().cloned().unwrap_or_default();
+        if to_name != title && to_name != from_name {
+            return Err(FsError::Forbidden);
+        }
+        self.check_title_free(&to_selector_set, &title, file.id)?;
+        self.add_selector_set_value(&file, &to_selector_set)?;
+        self.restore_files(vec![file.id])?;
+        Ok(())
+    }
+
+    // DELETE只会把文件放到回收站，回收站里的文件才会被彻底删除
+    fn delete_file(&self, paths: &Vec<String>) -> FsResult<()> {
+        let trashed = SimpleFileSystem::is_trash(paths.first());
+        let file = match self.resolve_file(paths)? {
+            (file, FileTarget::Body) => file,
+            (_, FileTarget::LabelDir) => return Err(FsError::Forbidden),
+            // 删除标签文件只会删掉对应的那一个值
+            (file, FileTarget::Label(name)) => {
+                let (key, value) = match name.split_once('=') {
+                    Some(v) => v,
+                    None => return Err(FsError::NotFound),
+                };
+                if BASIC_META_KEYS.contains(&key) {
+                    return Err(FsError::Forbidden);
+                }
+                let label = vec![KV::new(key.to_string(), value.to_string())];
+                self.remove_file_label_value(file.id, label)?;
+                return Ok(());
+            }
+        };
+        if trashed {
+            self.purge_files(vec![file.id])?;
+        } else {
+            self.trash_files(vec![file.id])?;
+        }
+        Ok(())
+    }
+
+    fn trash_files(&self, ids: Vec<u64>) -> Result<usize, FilesystemError> {
+        let now = format_time(SystemTime::now());
+        for id in &ids {
+            let params = SetLabelParams {
+                id: *id,
+                label: [(String::from(TRASHED_TIME), now.clone())]
+                    .into_iter()
+                    .collect(),
+            };
+            self.kv_file.set_label(&params)?;
+        }
+        Ok(ids.len())
+    }
+
+    fn restore_files(&self, ids: Vec<u64>) -> Result<usize, FilesystemError> {
+        let files = self.kv_file.list_file(&ListFileParams {
+            ids,
+            selectors: vec![],
+            filter: None,
+        })?;
+        let trashed_key = String::from(TRASHED_TIME);
+        let mut amount = 0;
+        for file in files.files {
+            let label: KVs = file
+                .label
+                .into_iter()
+                .filter(|kv| kv.key.eq(&trashed_key))
+                .collect();
+            if label.is_empty() {
+                continue;
+            }
+            self.kv_file.remove_label_value(&RemoveLabelValueParams { id: file.id, label })?;
+            amount += 1;
+        }
+        Ok(amount)
+    }
+
+    fn purge_files(&self, ids: Vec<u64>) -> Result<usize, FilesystemError> {
+        let trashed_key = String::from(TRASHED_TIME);
+        let ids: Vec<u64> = self
+            .kv_file
+            .list_file(&ListFileParams {
+                ids,
+                selectors: vec![],
+                filter: None,
+            })?
+            .files
+            .iter()
+            .filter(|f| KV::find_value(&f.label, &trashed_key).is_some())
+            .map(|f| f.id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let result = self.kv_file.remove_file(&RemoveFileParams { ids: ids.clone() })?;
+        for id in ids {
+            match self.blob.remove_blob(&RemoveBlobParams { id }) {
+                Ok(_) | Err(BlobStorageError::NotFound) => {}
+                Err(e) => return Err(FilesystemError::from(e)),
+            }
+        }
+        Ok(result.amount)
+    }
+
+    // 把筛选器组中文件还不满足的值追加到文件的标签上
+    fn add_selector_set_value(&self, file: &KVFile, selector_set: &SelectorSet) -> FsResult<()> {
+        let labels = KV::to_labels(&file.label);
+        let mut added = vec![];
+        for s in selector_set
+            .static_selectors
+            .iter()
+            .chain(selector_set.dynamic_selectors.iter())
+        {
+            if self.expand_selector(s)?.is_match(&labels) {
+                continue;
+            }
+            match SimpleFileSystem::selector_label(s)? {
+                Some(v) => added.push(KV::new(s.key.clone(), v)),
+                // 否定的条件不满足时，只能由用户自己去改标签
+                None => return Err(FsError::Forbidden),
+            }
+        }
+        let mut kvs = file.label.clone();
+        kvs.extend(added.iter().cloned());
+        self.check_filter(selector_set, &kvs)?;
+        self.add_file_label_value(file.id, added)?;
+        Ok(())
+    }
+
+    // 用新的值替换掉key原有的所有值
+    fn set_file_label(
+        &self,
+        id: u64,
+        key: String,
+        values: Vec<String>,
+    ) -> Result<(), FilesystemError> {
+        if values.is_empty() {
+            return Err(FilesystemError::InvalidArgument(format!(
+                "no value for label {}",
+                key
+            )));
+        }
+        let file = self.get_file(id)?;
+        let removed = KV::find_values(&file.label, &key)
+            .into_iter()
+            .map(|v| KV::new(key.clone(), v))
+            .collect();
+        let added = values.into_iter().map(|v| KV::new(key.clone(), v)).collect();
+        self.replace_file_label_value(id, removed, added)
+    }
+
+    // 基础信息由文件系统维护，不能删除或者改名
+    fn check_mutable_key(key: &String) -> Result<(), FilesystemError> {
+        if BASIC_META_KEYS.contains(&key.as_str()) {
+            return Err(FilesystemError::InvalidArgument(format!(
+                "{} is maintained by the filesystem",
+                key
+            )));
+        }
+        Ok(())
+    }
+
+    // 找到所有引用了这个key的筛选器组，不允许级联修改时直接报错
+    fn find_referencing_collections(
+        &self,
+        key: &String,
+        cascade: bool,
+    ) -> Result<Vec<SelectorSet>, FilesystemError> {
+        let collections: Vec<SelectorSet> = self
+            .selector_set_storage
+            .list_selector_set(&ListSelectorSetParams { names: vec![] })?
+            .selector_set
+            .into_iter()
+            .filter(|ss| {
+                ss.static_selectors
+                    .iter()
+                    .chain(ss.dynamic_selectors.iter())
+                    .chain(ss.filter.iter().flat_map(|f| f.selectors()))
+                    .any(|s| s.key.eq(key))
+            })
+            .collect();
+        if !collections.is_empty() && !cascade {
+            let names: Vec<String> = collections.iter().map(|ss| ss.name.clone()).collect();
+            return Err(FilesystemError::Conflict(format!(
+                "selector {} is used by collections {:?}",
+                key, names
+            )));
+        }
+        Ok(collections)
+    }
+
+    fn redefine_collections(&self, collections: Vec<SelectorSet>) -> Result<Vec<String>, FilesystemError> {
+        let names = collections.iter().map(|ss| ss.name.clone()).collect();
+        if collections.is_empty() {
+            return Ok(names);
+        }
+        let now = SystemTime::now();
+        let selector_sets = collections
+            .into_iter()
+            .map(|mut ss| {
+                ss.modified_time = Some(now);
+                ss
+            })
+            .collect();
+        self.selector_set_storage
+            .define_selector_set(&DefineSelectorSetParams { selector_sets })?;
+        Ok(names)
+    }
+
+    // 改名之后如果和已有的筛选器重复，就合并两者的值
+    fn rename_selector_in(selectors: &mut Selectors, key: &String, new_key: &String) {
+        let index = match selectors.iter().position(|s| s.key.eq(key)) {
+            Some(v) => v,
+            None => return,
+        };
+        let old = selectors.remove(index);
+        match selectors.iter_mut().find(|s| s.key.eq(new_key)) {
+            Some(s) => *s = s.merge(&old),
+            None => selectors.insert(
+                index,
+                Selector {
+                    key: new_key.clone(),
+                    ..old
+                },
+            ),
+        }
+    }
+
+    // 筛选器组引用的key必须已经定义过，或者已经出现在文件的标签上
+    fn check_selector_keys(&self, keys: Vec<String>) -> Result<(), FilesystemError> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let result = self
+            .selector_storage
+            .list_selector(&ListSelectorParams {
+                key: keys.clone(),
+                exclude_key: vec![],
+                value: vec![],
+            })?;
+        for key in keys {
+            if !result
+                .selectors
+                .iter()
+                .any(|s| s.key == key && !s.value.is_empty())
+            {
+                return Err(FilesystemError::InvalidArgument(format!(
+                    "selector {} is not defined",
+                    key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn get_file(&self, id: u64) -> Result<KVFile, FilesystemError> {
+        let files = self
+            .kv_file
+            .list_file(&ListFileParams {
+                ids: vec![id],
+                selectors: vec![],
+                filter: None,
+            })?
+            .files;
+        match files.into_iter().next() {
+            Some(v) => Ok(v),
+            None => Err(FilesystemError::NotFound),
+        }
+    }
+
+    // values为空时删除这个标签
+    pub(super) fn update_file_label(
+        &self,
+        id: u64,
+        key: String,
+        values: Vec<String>,
+    ) -> Result<(), FilesystemError> {
+        if !values.is_empty() {
+            return self.set_file_label(id, key, values);
+        }
+        let file = self.get_file(id)?;
+        let removed = KV::find_values(&file.label, &key)
+            .into_iter()
+            .map(|v| KV::new(key.clone(), v))
+            .collect();
+        self.remove_file_label_value(id, removed)
+    }
+
+    fn add_file_label_value(&self, id: u64, label: KVs) -> Result<(), FilesystemError> {
+        if label.is_empty() {
+            return Ok(());
+        }
+        self.kv_file
+            .add_label_value(&AddLabelValueParams { id, label })?;
+        Ok(())
+    }
+
+    fn remove_file_label_value(&self, id: u64, label: KVs) -> Result<(), FilesystemError> {
+        if label.is_empty() {
+            return Ok(());
+        }
+        self.kv_file
+            .remove_label_value(&RemoveLabelValueParams { id, label })?;
+        Ok(())
+    }
+
+    fn replace_file_label_value(&self, id: u64, removed: KVs, added: KVs) -> Result<(), FilesystemError> {
+        if removed.is_empty() && added.is_empty() {
+            return Ok(());
+        }
+        self.kv_file
+            .replace_label_value(&ReplaceLabelValueParams { id, removed, added })?;
+        Ok(())
+    }
+
+    // 只有文件本体有属性，文件的每个标签都是一个属性
+    fn get_file_props(&self, paths: &Vec<String>) -> FsResult<Vec<DavProp>> {
+        let file = match self.resolve_file(paths)? {
+            (file, FileTarget::Body) => file,
+            _ => return Err(FsError::NotFound),
+        };
+        Ok(KV::to_labels(&file.label)
+            .iter()
+            .filter_map(|(k, vs)| labelprop::to_prop(k, vs))
+            .collect())
+    }
+
+    // RFC 4918 要求 PROPPATCH 要么全部生效要么全部不生效，有一个不合法时其他的都返回 424
+    fn patch_file_props(
+        &self,
+        paths: &Vec<String>,
+        patch: Vec<(bool, DavProp)>,
+    ) -> FsResult<Vec<(StatusCode, DavProp)>> {
+        let file = match self.resolve_file(paths)? {
+            (file, FileTarget::Body) => file,
+            _ => return Err(FsError::NotFound),
+        };
+        let checked: Vec<Result<Option<Vec<String>>, StatusCode>> = patch
+            .iter()
+            .map(|(set, prop)| SimpleFileSystem::check_file_prop(*set, prop))
+            .collect();
+        let status = if checked.iter().any(|r| r.is_err()) {
+            None
+        } else {
+            // 同一个属性出现多次时以最后一次为准
+            let mut changes: BTreeMap<String, Option<Vec<String>>> = BTreeMap::new();
+            for ((_, prop), values) in patch.iter().zip(checked.iter()) {
+                changes.insert(prop.name.clone(), values.clone().unwrap());
             }
+            let labels = KV::to_labels(&file.label);
+            let mut removed = vec![];
+            let mut added = vec![];
+            for (key, values) in changes {
+                for v in labels.get(&key).into_iter().flatten() {
+                    removed.push(KV::new(key.clone(), v.clone()));
+                }
+                for v in values.into_iter().flatten() {
+                    added.push(KV::new(key.clone(), v));
+                }
+            }
+            Some(match self.replace_file_label_value(file.id, removed, added) {
+                Ok(_) => StatusCode::OK,
+                Err(e) => SimpleFileSystem::prop_status(e),
+            })
+        };
+        Ok(patch
+            .into_iter()
+            .zip(checked)
+            .map(|((_, prop), result)| {
+                let status = match (status, result) {
+                    (Some(status), _) => status,
+                    (None, Err(status)) => status,
+                    (None, Ok(_)) => StatusCode::FAILED_DEPENDENCY,
+                };
+                (status, labelprop::to_name(prop))
+            })
+            .collect())
+    }
+
+    // 设置时返回新的值，删除时返回None，不能修改的属性返回对应的状态码
+    fn check_file_prop(set: bool, prop: &DavProp) -> Result<Option<Vec<String>>, StatusCode> {
+        // 基础信息由文件系统维护，不允许修改
+        if !labelprop::is_label_prop(prop) || BASIC_META_KEYS.contains(&prop.name.as_str()) {
+            return Err(StatusCode::FORBIDDEN);
         }
-        // 筛选器还没有满，找到下一个筛选项，并将可选结果以目录的形式返回
-        if !selector_set.is_full() {
-            info!("return next filter");
-            return self.read_selecting_dir_stream(selector_set, meta);
+        if !set {
+            return Ok(None);
         }
-        // 参数量刚好填满筛选器，说明需要返回文件列表
-        if tokens.is_empty() {
-            info!("return file list");
-            return self.read_matching_dir_stream(selector_set, meta);
+        match labelprop::to_values(prop) {
+            Some(values) if !values.is_empty() => Ok(Some(values)),
+            _ => Err(StatusCode::CONFLICT),
+        }
+    }
+
+    fn prop_status(err: FilesystemError) -> StatusCode {
+        match err {
+            FilesystemError::NotFound => StatusCode::NOT_FOUND,
+            FilesystemError::InvalidArgument(_) => StatusCode::FORBIDDEN,
+            FilesystemError::Conflict(_) | FilesystemError::DuplicateTitle(_) => StatusCode::CONFLICT,
+            FilesystemError::Backend(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
-        // 进入到下一层，返回文件信息
-        info!("return file meta");
-        return self.read_file_meta_stream(selector_set, &mut tokens, meta);
+    }
+
+    // 有多个候选值时无法确定要写到标签上的是哪一个
+    fn selector_value(selector: &Selector) -> FsResult<String> {
+        if selector.value.len() != 1 {
+            return Err(FsError::Forbidden);
+        }
+        Ok(selector.value.iter().next().unwrap().clone())
+    }
+
+    // 新文件要写上的标签，否定的条件对没有这个key的文件天然成立，不用写
+    fn selector_label(selector: &Selector) -> FsResult<Option<String>> {
+        match selector.op {
+            SelectorOp::In => Ok(Some(SimpleFileSystem::selector_value(selector)?)),
+            _ if selector.is_negative() => Ok(None),
+            _ => Err(FsError::Forbidden),
+        }
+    }
+
+    // 新文件的标签由静态筛选器、路径中的参数以及文件名组成
+    fn add_file_by_selector_set(&self, selector_set: &SelectorSet, title: String) -> FsResult<u64> {
+        let mut label = vec![];
+        for s in selector_set
+            .static_selectors
+            .iter()
+            .chain(selector_set.dynamic_selectors.iter())
+        {
+            if let Some(v) = SimpleFileSystem::selector_label(s)? {
+                label.push(KV::new(s.key.clone(), v));
+            }
+        }
+        label.push(KV::new(String::from(TITLE), title));
+        self.check_filter(selector_set, &label)?;
+        Ok(self.kv_file.add_file(&AddFileParams { label })?.id)
     }
 
     fn read_root_dir_stream<'a>(
         &'a self,
         meta: ReadDirMeta,
     ) -> FsResult<FsStream<Box<dyn DavDirEntry>>> {
-        let selector_sets = self
-            .selector_set_storage
-            .list_selector_set(&ListSelectorSetParams { names: vec![] });
-        match selector_sets {
-            Err(_) => Err(FsError::GeneralFailure),
-            Ok(result) => {
-                let dirs: Vec<Box<dyn DavDirEntry>> = result
-                    .selector_set
-                    .iter()
-                    .map(StaticDir::from)
-                    .map(|x| Box::new(x) as Box<dyn DavDirEntry>)
-                    .collect();
-                Ok(Box::pin(iter(dirs)))
-            }
-        }
+        let mut dirs: Vec<Box<dyn DavDirEntry>> = self
+            .list_collection_group(&vec![])?
+            .into_iter()
+            .map(|x| Box::new(x) as Box<dyn DavDirEntry>)
+            .collect();
+        dirs.push(Box::new(StaticDir::from(&String::from(TRASH_DIR))));
+        Ok(Box::pin(iter(dirs)))
+    }
+
+    fn read_trash_dir_stream<'a>(
+        &'a self,
+        meta: ReadDirMeta,
+    ) -> FsResult<FsStream<Box<dyn DavDirEntry>>> {
+        let mut files = self.list_file_by_selectors(vec![], true)?;
+        SimpleFileSystem::sort_files(&mut files, &vec![]);
+        let dirs = SimpleFileSystem::file_dir_entries(&files, None);
+        Ok(Box::pin(iter(dirs)))
+    }
+
+    fn read_query_dir_stream<'a>(
+        &'a self,
+        query: &String,
+        meta: ReadDirMeta,
+    ) -> FsResult<FsStream<Box<dyn DavDirEntry>>> {
+        let filter = SimpleFileSystem::parse_query_dir(query)?;
+        let mut files = self.list_file_by_filter(vec![], Some(&filter), false)?;
+        SimpleFileSystem::sort_files(&mut files, &vec![]);
+        let dirs = SimpleFileSystem::file_dir_entries(&files, None);
+        Ok(Box::pin(iter(dirs)))
     }
 
     fn read_selecting_dir_stream<'a>(
@@ -130,22 +1360,53 @@ impl SimpleFileSystem {
             Some(v) => v.clone(),
             None => return Err(FsError::NotFound),
         };
-        let next_selector_with_options =
-            match self.kv_file.get_selector_by_key(next_selector.get_key()) {
-                Ok(v) => v,
-                Err(_) => return Err(FsError::NotFound),
-            };
-        for value in &next_selector_with_options.value {
-            info!("value={}", value);
+        let modified_time = SimpleFileSystem::selector_set_time(&selector_set);
+        // 只列出满足已经选好的条件的文件上出现过的值
+        let mut selectors = selector_set.static_selectors.clone();
+        selectors.extend(
+            selector_set
+                .dynamic_selectors
+                .iter()
+                .filter(|s| !s.is_missing_value())
+                .cloned(),
+        );
+        let files = self.list_file_by_filter(selectors, selector_set.filter.as_ref(), false)?;
+        // 别名合并到规范值的目录里
+        let aliases = self.selector_storage.get_aliases_by_key(&next_selector.key)?;
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut unset = 0;
+        for file in &files {
+            let values: BTreeSet<String> = KV::find_values(&file.label, &next_selector.key)
+                .into_iter()
+                .filter(|v| !v.is_empty())
+                .map(|v| aliases.get(&v).cloned().unwrap_or(v))
+                .collect();
+            if values.is_empty() {
+                unset += 1;
+            }
+            for v in values {
+                *counts.entry(v).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| natural_cmp(&a.0, &b.0));
+        // 排序规则里指定了这个key倒序时，值的目录也倒序
+        if selector_set
+            .sort
+            .iter()
+            .any(|s| s.key == next_selector.key && s.descending)
+        {
+            counts.reverse();
         }
-        let mut dirs: Vec<Box<dyn DavDirEntry>> = next_selector_with_options
-            .value
+        let mut dirs: Vec<Box<dyn DavDirEntry>> = counts
             .iter()
-            .map(StaticDir::from)
+            .map(|(v, count)| StaticDir::new(&self.value_dir_name(v, *count), modified_time))
             .map(|x| Box::new(x) as Box<dyn DavDirEntry>)
             .collect();
-        // 不记得有什么用了，先注释掉通过编译
-        // dirs.push(Box::new(StaticFile::new(next_selector.key, None, None)) as Box<dyn DavDirEntry>);
+        if unset > 0 {
+            let name = self.value_dir_name(UNSET_DIR, unset);
+            dirs.push(Box::new(StaticDir::new(&name, modified_time)));
+        }
         Ok(Box::pin(iter(dirs)))
     }
 
@@ -154,53 +1415,32 @@ impl SimpleFileSystem {
         selector_set: SelectorSet,
         meta: ReadDirMeta,
     ) -> FsResult<FsStream<Box<dyn DavDirEntry>>> {
-        let mut selectors = selector_set.static_selectors.clone();
-        selectors.extend(selector_set.dynamic_selectors);
-        let files = match self.kv_file.list_file(&ListFileParams {
-            selectors: selectors,
-            ids: vec![],
-        }) {
-            Ok(r) => r.files,
-            Err(_) => return Err(FsError::NotFound),
-        };
-        let dirs: Vec<Box<dyn DavDirEntry>> = files
-            .iter()
-            .map(StaticDir::from)
-            .map(|x| Box::new(x) as Box<dyn DavDirEntry>)
-            .collect();
+        let selectors = SimpleFileSystem::selector_set_selectors(&selector_set);
+        let mut files = self.list_file_by_filter(selectors, selector_set.filter.as_ref(), false)?;
+        SimpleFileSystem::sort_files(&mut files, &selector_set.sort);
+        let template = SimpleFileSystem::name_template(&selector_set)?;
+        let dirs = SimpleFileSystem::file_dir_entries(&files, template.as_deref());
         Ok(Box::pin(iter(dirs)))
     }
 
-    fn read_file_meta_stream<'a>(
+    // 标签目录下每个标签值一个 key=value 文件，文件本体不是目录
+    fn read_label_dir_stream<'a>(
         &'a self,
-        selector_set: SelectorSet,
-        tokens: &mut VecDeque<String>,
+        paths: &Vec<String>,
         meta: ReadDirMeta,
     ) -> FsResult<FsStream<Box<dyn DavDirEntry>>> {
-        // 不允许重名，所以此处只用TITLE一个做筛选即可
-        let file = match self.kv_file.list_file(&ListFileParams {
-            selectors: vec![Selector::new(
-                String::from(TITLE),
-                vec![tokens.pop_front().unwrap()],
-            )],
-            ids: vec![],
-        }) {
-            Ok(r) => match r.files.get(0) {
-                Some(v) => v.clone(),
-                None => return Err(FsError::NotFound),
-            },
-            Err(_) => return Err(FsError::NotFound),
+        let file = match self.resolve_file(paths)? {
+            (file, FileTarget::LabelDir) => file,
+            _ => return Err(FsError::NotFound),
         };
         info!("result file: {:?}", file);
-        let mut dirs: Vec<Box<dyn DavDirEntry>> = file
+        let dirs: Vec<Box<dyn DavDirEntry>> = file
             .label
             .iter()
             .filter(|kv| !BASIC_META_KEYS.contains(&kv.key.as_str()))
             .map(StaticFile::from)
             .map(|x| Box::new(x) as Box<dyn DavDirEntry>)
             .collect();
-        // 不记得有什么用了，先注释掉通过编译
-        // StaticFile::new(name, body, modified_time);
         Ok(Box::pin(iter(dirs)))
     }
 }
@@ -211,9 +1451,18 @@ impl DavFileSystem for SimpleFileSystem {
         path: &'a webdav_handler::davpath::DavPath,
         options: webdav_handler::fs::OpenOptions,
     ) -> webdav_handler::fs::FsFuture<Box<dyn DavFile>> {
-        // 1. 打开KV对
-        // 2. 打开文件本体
-        todo!()
+        async move {
+            let paths = match SimpleFileSystem::split_path(path) {
+                Ok(paths) => paths,
+                Err(_) => return Err(FsError::NotFound),
+            };
+            if options.write || options.append || options.create || options.create_new {
+                self.open_writable(&paths, &options)
+            } else {
+                self.open_file(&paths)
+            }
+        }
+        .boxed()
     }
 
     fn read_dir<'a>(
@@ -230,13 +1479,119 @@ impl DavFileSystem for SimpleFileSystem {
         .boxed()
     }
 
+    fn rename<'a>(
+        &'a self,
+        from: &'a webdav_handler::davpath::DavPath,
+        to: &'a webdav_handler::davpath::DavPath,
+    ) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            match (
+                SimpleFileSystem::split_path(from),
+                SimpleFileSystem::split_path(to),
+            ) {
+                (Ok(from), Ok(to)) => self.rename_file(&from, &to),
+                _ => Err(FsError::NotFound),
+            }
+        }
+        .boxed()
+    }
+
+    fn copy<'a>(
+        &'a self,
+        from: &'a webdav_handler::davpath::DavPath,
+        to: &'a webdav_handler::davpath::DavPath,
+    ) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            match (
+                SimpleFileSystem::split_path(from),
+                SimpleFileSystem::split_path(to),
+            ) {
+                (Ok(from), Ok(to)) => self.copy_file(&from, &to),
+                _ => Err(FsError::NotFound),
+            }
+        }
+        .boxed()
+    }
+
+    fn remove_file<'a>(
+        &'a self,
+        path: &'a webdav_handler::davpath::DavPath,
+    ) -> webdav_handler::fs::FsFuture<()> {
+        async move {
+            match SimpleFileSystem::split_path(path) {
+                Ok(paths) => self.delete_file(&paths),
+                Err(_) => Err(FsError::NotFound),
+            }
+        }
+        .boxed()
+    }
+
+    fn have_props<'a>(&'a self, path: &'a webdav_handler::davpath::DavPath) -> BoxFuture<'a, bool> {
+        async move {
+            match SimpleFileSystem::split_path(path) {
+                Ok(paths) => self.get_file_props(&paths).is_ok(),
+                Err(_) => false,
+            }
+        }
+        .boxed()
+    }
+
+    fn get_props<'a>(
+        &'a self,
+        path: &'a webdav_handler::davpath::DavPath,
+        do_content: bool,
+    ) -> webdav_handler::fs::FsFuture<Vec<DavProp>> {
+        async move {
+            match SimpleFileSystem::split_path(path) {
+                Ok(paths) => self.get_file_props(&paths),
+                Err(_) => Err(FsError::NotFound),
+            }
+        }
+        .boxed()
+    }
+
+    fn get_prop<'a>(
+        &'a self,
+        path: &'a webdav_handler::davpath::DavPath,
+        prop: DavProp,
+    ) -> webdav_handler::fs::FsFuture<Vec<u8>> {
+        async move {
+            let paths = match SimpleFileSystem::split_path(path) {
+                Ok(paths) => paths,
+                Err(_) => return Err(FsError::NotFound),
+            };
+            self.get_file_props(&paths)?
+                .into_iter()
+                .find(|p| p.name == prop.name && p.namespace == prop.namespace)
+                .and_then(|p| p.xml)
+                .ok_or(FsError::NotFound)
+        }
+        .boxed()
+    }
+
+    fn patch_props<'a>(
+        &'a self,
+        path: &'a webdav_handler::davpath::DavPath,
+        patch: Vec<(bool, DavProp)>,
+    ) -> webdav_handler::fs::FsFuture<Vec<(StatusCode, DavProp)>> {
+        async move {
+            match SimpleFileSystem::split_path(path) {
+                Ok(paths) => self.patch_file_props(&paths, patch),
+                Err(_) => Err(FsError::NotFound),
+            }
+        }
+        .boxed()
+    }
+
     fn metadata<'a>(
         &'a self,
         path: &'a webdav_handler::davpath::DavPath,
     ) -> webdav_handler::fs::FsFuture<Box<dyn webdav_handler::fs::DavMetaData>> {
-        async {
-            let meta = StaticDir::new(&String::from("root"), SystemTime::now());
-            Ok(Box::new(meta) as Box<dyn DavMetaData>)
+        async move {
+            match SimpleFileSystem::split_path(path) {
+                Ok(paths) => self.read_metadata(&paths),
+                Err(_) => Err(FsError::NotFound),
+            }
         }
         .boxed()
     }
@@ -247,6 +1602,7 @@ impl CollectionFS for SimpleFileSystem {
         &'a self,
         params: &'a DefineCollectionParams,
     ) -> Result<DefineCollectionResult, FilesystemError> {
+        self.check_collection_name(&params.selector_set.name, None)?;
         match self
             .selector_set_storage
             .define_selector_set(&DefineSelectorSetParams {
@@ -275,21 +1631,659 @@ impl CollectionFS for SimpleFileSystem {
         &'a self,
         params: &'a collectionfs::AddFileParams,
     ) -> Result<AddFileResult, FilesystemError> {
+        if !matches!(KV::find_values(&params.kvs, &String::from(TITLE)).as_slice(), [title] if !title.is_empty()) {
+            return Err(FilesystemError::InvalidArgument(String::from(
+                "file must have exactly one non-empty title",
+            )));
+        }
         match self.kv_file.add_file(&AddFileParams {
             label: params.kvs.clone(),
         }) {
-            Ok(_) => Ok(AddFileResult {}),
+            Ok(r) => Ok(AddFileResult {
+                id: r.id,
+                label: r.label,
+            }),
             Err(e) => Err(FilesystemError::from(e)),
         }
     }
 
+    fn update_collection<'a>(
+        &'a self,
+        params: &'a UpdateCollectionParams,
+    ) -> Result<UpdateCollectionResult, FilesystemError> {
+        if let Some(name) = &params.new_name {
+            self.check_collection_name(name, Some(&params.name))?;
+        }
+        let keys = params
+            .add_static_selectors
+            .iter()
+            .chain(params.filter.iter().flat_map(|f| f.selectors()))
+            .map(|s| s.key.clone())
+            .collect();
+        self.check_selector_keys(keys)?;
+        let result = self.selector_set_storage.update_selector_set(params)?;
+        Ok(UpdateCollectionResult {
+            collection: result.selector_set,
+        })
+    }
+
+    fn remove_selector<'a>(
+        &'a self,
+        params: &'a RemoveSelectorParams,
+    ) -> Result<RemoveSelectorResult, FilesystemError> {
+        SimpleFileSystem::check_mutable_key(&params.key)?;
+        let collections = self.find_referencing_collections(&params.key, params.cascade)?;
+        let result = self
+            .selector_storage
+            .remove_selector(&crate::adapter::storage::RemoveSelectorParams {
+                key: params.key.clone(),
+            })?;
+        let collections = collections
+            .into_iter()
+            .map(|mut ss| {
+                ss.static_selectors.retain(|s| !s.key.eq(&params.key));
+                ss.dynamic_selectors.retain(|s| !s.key.eq(&params.key));
+                ss.filter = ss.filter.and_then(|f| f.without_key(&params.key));
+                ss
+            })
+            .collect();
+        Ok(RemoveSelectorResult {
+            amount: result.amount,
+            collections: self.redefine_collections(collections)?,
+        })
+    }
+
+    fn rename_selector<'a>(
+        &'a self,
+        params: &'a RenameSelectorParams,
+    ) -> Result<RenameSelectorResult, FilesystemError> {
+        SimpleFileSystem::check_mutable_key(&params.key)?;
+        SimpleFileSystem::check_mutable_key(&params.new_key)?;
+        let collections = self.find_referencing_collections(&params.key, params.cascade)?;
+        let result = self
+            .selector_storage
+            .rename_selector(&crate::adapter::storage::RenameSelectorParams {
+                key: params.key.clone(),
+                new_key: params.new_key.clone(),
+            })?;
+        let collections = collections
+            .into_iter()
+            .map(|mut ss| {
+                SimpleFileSystem::rename_selector_in(&mut ss.static_selectors, &params.key, &params.new_key);
+                SimpleFileSystem::rename_selector_in(&mut ss.dynamic_selectors, &params.key, &params.new_key);
+                ss.filter = ss.filter.map(|f| f.rename_key(&params.key, &params.new_key));
+                ss
+            })
+            .collect();
+        Ok(RenameSelectorResult {
+            amount: result.amount,
+            collections: self.redefine_collections(collections)?,
+        })
+    }
+
+    fn define_alias<'a>(
+        &'a self,
+        params: &'a DefineAliasParams,
+    ) -> Result<DefineAliasResult, FilesystemError> {
+        SimpleFileSystem::check_mutable_key(&params.key)?;
+        Ok(self.selector_storage.define_alias(params)?)
+    }
+
+    fn remove_alias<'a>(
+        &'a self,
+        params: &'a RemoveAliasParams,
+    ) -> Result<RemoveAliasResult, FilesystemError> {
+        Ok(self.selector_storage.remove_alias(params)?)
+    }
+
+    fn list_alias<'a>(
+        &'a self,
+        params: &'a ListAliasParams,
+    ) -> Result<ListAliasResult, FilesystemError> {
+        Ok(self.selector_storage.list_alias(params)?)
+    }
+
+    fn merge_value<'a>(
+        &'a self,
+        params: &'a MergeValueParams,
+    ) -> Result<MergeValueResult, FilesystemError> {
+        SimpleFileSystem::check_mutable_key(&params.key)?;
+        Ok(self.selector_storage.merge_value(params)?)
+    }
+
+    fn list_collection<'a>(
+        &'a self,
+        params: &'a ListCollectionParams,
+    ) -> Result<ListCollectionResult, FilesystemError> {
+        let result = self
+            .selector_set_storage
+            .list_selector_set(&ListSelectorSetParams {
+                names: params.names.clone(),
+            })?;
+        Ok(ListCollectionResult {
+            collections: result.selector_set,
+        })
+    }
+
+    fn list_selector<'a>(
+        &'a self,
+        params: &'a crate::ListSelectorParams,
+    ) -> Result<crate::ListSelectorResult, FilesystemError> {
+        // 和目录列表保持一致，只保留默认值和回收站以外的文件上的值
+        let mut params = params.clone();
+        params.exclude_key.push(String::from(TRASHED_TIME));
+        let result = self.selector_storage.list_selector(&params)?;
+        Ok(crate::ListSelectorResult {
+            selectors: result.selectors,
+            value_types: result.value_types,
+        })
+    }
+
+    fn list_file<'a>(
+        &'a self,
+        params: &'a collectionfs::ListFileParams,
+    ) -> Result<collectionfs::ListFileResult, FilesystemError> {
+        let files = self
+            .list_file_by_filter(params.selectors.clone(), params.filter.as_ref(), false)?
+            .into_iter()
+            .filter(|f| params.ids.is_empty() || params.ids.contains(&f.id))
+            .collect();
+        Ok(collectionfs::ListFileResult { files })
+    }
+
+    fn remove_file<'a>(
+        &'a self,
+        params: &'a collectionfs::RemoveFileParams,
+    ) -> Result<collectionfs::RemoveFileResult, FilesystemError> {
+        let ids = self
+            .kv_file
+            .list_file(&ListFileParams {
+                ids: params.ids.clone(),
+                selectors: vec![],
+                filter: None,
+            })?
+            .files
+            .into_iter()
+            .map(|f| f.id)
+            .collect();
+        let amount = self.trash_files(ids)?;
+        Ok(collectionfs::RemoveFileResult { amount })
+    }
+
+    fn set_label<'a>(
+        &'a self,
+        params: &'a collectionfs::SetLabelParams,
+    ) -> Result<collectionfs::SetLabelResult, FilesystemError> {
+        self.get_file(params.id)?;
+        for (key, values) in &params.label {
+            if key.as_str() == TITLE {
+                // 重名的文件在目录里用 (#id) 区分，这里只检查标题是否合法
+                if !matches!(values.as_slice(), [title] if !title.is_empty()) {
+                    return Err(FilesystemError::InvalidArgument(String::from(
+                        "file must have exactly one non-empty title",
+                    )));
+                }
+            } else if BASIC_META_KEYS.contains(&key.as_str()) {
+                return Err(FilesystemError::InvalidArgument(format!(
+                    "{} is maintained by the filesystem",
+                    key
+                )));
+            }
+        }
+        for (key, values) in &params.label {
+            self.update_file_label(params.id, key.clone(), values.clone())?;
+        }
+        Ok(collectionfs::SetLabelResult {
+            file: self.get_file(params.id)?,
+        })
+    }
+
+    fn restore_file<'a>(
+        &'a self,
+        params: &'a RestoreFileParams,
+    ) -> Result<RestoreFileResult, FilesystemError> {
+        let amount = self.restore_files(params.ids.clone())?;
+        Ok(RestoreFileResult { amount })
+    }
+
+    fn purge_file<'a>(
+        &'a self,
+        params: &'a PurgeFileParams,
+    ) -> Result<PurgeFileResult, FilesystemError> {
+        let amount = self.purge_files(params.ids.clone())?;
+        Ok(PurgeFileResult { amount })
+    }
+
     fn define_selector<'a>(
         &'a self,
         params: &'a crate::DefineSelectorParams,
     ) -> Result<crate::DefineSelectorResult, crate::FilesystemError> {
+        if BASIC_META_KEYS.contains(&params.key.as_str()) {
+            return Err(FilesystemError::InvalidArgument(format!(
+                "{} is maintained by the filesystem",
+                params.key
+            )));
+        }
         match self.selector_storage.define_selector(&params) {
             Ok(_) => Ok(DefineSelectorResult {}),
             Err(e) => Err(FilesystemError::from(e)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::adapter::storage::{Labels, LocalBlobStorage, MemFileKVFileStorage, MemSelectorSetStorage};
+
+    fn new_fs() -> SimpleFileSystem {
+        let kv = Arc::new(MemFileKVFileStorage::new());
+        let blob_dir = std::env::temp_dir().join(format!("soapdav-{}", uuid::Uuid::new_v4()));
+        SimpleFileSystem::new(
+            Arc::new(MemSelectorSetStorage::new()),
+            kv.clone(),
+            kv,
+            Arc::new(LocalBlobStorage::new(blob_dir).unwrap()),
+        )
+    }
+
+    // 按作者浏览的筛选器组 manga
+    fn manga_fs() -> SimpleFileSystem {
+        let fs = new_fs();
+        define_collection(&fs, collection("manga", &["author"]));
+        fs
+    }
+
+    fn collection(name: &str, keys: &[&str]) -> SelectorSet {
+        let mut selector_set = SelectorSet::new(&name.to_string());
+        selector_set.dynamic_selectors = keys
+            .iter()
+            .map(|k| Selector::new(k.to_string(), vec![]))
+            .collect();
+        selector_set
+    }
+
+    fn define_collection(fs: &SimpleFileSystem, selector_set: SelectorSet) {
+        fs.define_collection(&DefineCollectionParams { selector_set }).unwrap();
+    }
+
+    fn paths(path: &str) -> Vec<String> {
+        path.split('/').filter(|s| !s.is_empty()).map(String::from).collect()
+    }
+
+    // 和 PUT 一样，打开之后写入内容再 flush
+    fn write(fs: &SimpleFileSystem, path: &str, body: &str) -> FsResult<()> {
+        let options = OpenOptions {
+            write: true,
+            create: true,
+            truncate: true,
+            ..Default::default()
+        };
+        let mut file = fs.open_writable(&paths(path), &options)?;
+        block_on(file.write_bytes(Bytes::from(body.to_string())))?;
+        block_on(file.flush())
+    }
+
+    fn read(fs: &SimpleFileSystem, path: &str) -> FsResult<String> {
+        let mut file = fs.open_file(&paths(path))?;
+        let body = block_on(file.read_bytes(1024))?;
+        Ok(String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn list(fs: &SimpleFileSystem, path: &str) -> FsResult<Vec<String>> {
+        let stream = fs.read_dir_stream(&paths(path), ReadDirMeta::None)?;
+        Ok(block_on(stream.collect::<Vec<_>>())
+            .iter()
+            .map(|e| String::from_utf8(e.name()).unwrap())
+            .collect())
+    }
+
+    // 只列出文件本体，跳过旁边的标签目录
+    fn list_files(fs: &SimpleFileSystem, path: &str) -> Vec<String> {
+        list(fs, path)
+            .unwrap()
+            .into_iter()
+            .filter(|n| !n.ends_with(LABEL_DIR_SUFFIX))
+            .collect()
+    }
+
+    // 按标题找到文件的标签，回收站中的文件也能找到
+    fn labels(fs: &SimpleFileSystem, title: &str) -> Option<Labels> {
+        let params = ListFileParams {
+            ids: vec![],
+            selectors: vec![Selector::new(String::from(TITLE), vec![title.to_string()])],
+            filter: None,
+        };
+        let files = fs.kv_file.list_file(&params).unwrap().files;
+        files.first().map(|f| KV::to_labels(&f.label))
+    }
+
+    fn values(fs: &SimpleFileSystem, title: &str, key: &str) -> Vec<String> {
+        let mut values = labels(fs, title).unwrap().remove(key).unwrap_or_default();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn test_put_and_read() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        assert_eq!(read(&fs, "/manga/oda/One Piece").unwrap(), "hello");
+        assert_eq!(values(&fs, "One Piece", "author"), vec!["oda"]);
+        assert_eq!(values(&fs, "One Piece", BODY_SIZE), vec!["5"]);
+        assert_eq!(list(&fs, "/manga").unwrap(), vec!["oda"]);
+        assert_eq!(list_files(&fs, "/manga/oda"), vec!["One Piece"]);
+        // 筛选器没有填满的路径上不能放文件
+        assert!(matches!(write(&fs, "/manga/Naruto", "x"), Err(FsError::Forbidden)));
+        // 覆盖写入之后大小跟着变
+        write(&fs, "/manga/oda/One Piece", "hi").unwrap();
+        assert_eq!(read(&fs, "/manga/oda/One Piece").unwrap(), "hi");
+        assert_eq!(values(&fs, "One Piece", BODY_SIZE), vec!["2"]);
+        // flush 之前旧的内容和大小都不变
+        let options = OpenOptions {
+            write: true,
+            truncate: true,
+            ..Default::default()
+        };
+        let mut file = fs.open_writable(&paths("/manga/oda/One Piece"), &options).unwrap();
+        block_on(file.write_bytes(Bytes::from_static(b"bye"))).unwrap();
+        assert_eq!(read(&fs, "/manga/oda/One Piece").unwrap(), "hi");
+        assert_eq!(values(&fs, "One Piece", BODY_SIZE), vec!["2"]);
+        block_on(file.flush()).unwrap();
+        assert_eq!(read(&fs, "/manga/oda/One Piece").unwrap(), "bye");
+        assert_eq!(values(&fs, "One Piece", BODY_SIZE), vec!["3"]);
+        // 没有 create 时不会新建文件
+        let result = fs.open_writable(&paths("/manga/oda/Vagabond"), &options);
+        assert!(matches!(result, Err(FsError::NotFound)));
+        assert_eq!(list_files(&fs, "/manga/oda"), vec!["One Piece"]);
+    }
+
+    #[test]
+    fn test_move() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        // 移动改的是路径上对应的标签
+        fs.rename_file(&paths("/manga/oda/One Piece"), &paths("/manga/togashi/One Piece"))
+            .unwrap();
+        assert_eq!(values(&fs, "One Piece", "author"), vec!["togashi"]);
+        assert_eq!(read(&fs, "/manga/togashi/One Piece").unwrap(), "hello");
+        // 改名改的是标题
+        fs.rename_file(&paths("/manga/togashi/One Piece"), &paths("/manga/togashi/Hunter"))
+            .unwrap();
+        assert_eq!(values(&fs, "Hunter", "author"), vec!["togashi"]);
+        assert!(read(&fs, "/manga/togashi/One Piece").is_err());
+        // 不能改成已有文件的标题
+        write(&fs, "/manga/togashi/Yu Yu", "x").unwrap();
+        let result = fs.rename_file(&paths("/manga/togashi/Hunter"), &paths("/manga/togashi/Yu Yu"));
+        assert!(matches!(result, Err(FsError::Exists)));
+    }
+
+    #[test]
+    fn test_copy() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        // 复制只是多加一个值，两个目录下都能看到同一份内容
+        fs.copy_file(&paths("/manga/oda/One Piece"), &paths("/manga/toriyama/One Piece"))
+            .unwrap();
+        assert_eq!(values(&fs, "One Piece", "author"), vec!["oda", "toriyama"]);
+        assert_eq!(read(&fs, "/manga/toriyama/One Piece").unwrap(), "hello");
+        assert_eq!(list_files(&fs, "/manga/toriyama"), vec!["One Piece"]);
+        let result = fs.copy_file(&paths("/manga/oda/One Piece"), &paths("/manga/toriyama/Two Piece"));
+        assert!(matches!(result, Err(FsError::Forbidden)));
+        // 移动只替换路径上的那个值
+        fs.rename_file(&paths("/manga/oda/One Piece"), &paths("/manga/togashi/One Piece"))
+            .unwrap();
+        assert_eq!(values(&fs, "One Piece", "author"), vec!["togashi", "toriyama"]);
+    }
+
+    #[test]
+    fn test_trash() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        fs.delete_file(&paths("/manga/oda/One Piece")).unwrap();
+        assert!(labels(&fs, "One Piece").unwrap().contains_key(TRASHED_TIME));
+        assert!(read(&fs, "/manga/oda/One Piece").is_err());
+        assert!(list_files(&fs, "/manga/oda").is_empty());
+        assert_eq!(list_files(&fs, "/.trash"), vec!["One Piece"]);
+        assert_eq!(read(&fs, "/.trash/One Piece").unwrap(), "hello");
+        // 只在回收站中的文件上出现的值不再列出
+        let params = ListSelectorParams {
+            key: vec![String::from("author")],
+            exclude_key: vec![],
+            value: vec![],
+        };
+        let result = CollectionFS::list_selector(&fs, &params).unwrap();
+        assert!(!result.selectors[0].value.contains("oda"));
+        // 从回收站移出去就是还原
+        fs.rename_file(&paths("/.trash/One Piece"), &paths("/manga/oda/One Piece"))
+            .unwrap();
+        assert_eq!(read(&fs, "/manga/oda/One Piece").unwrap(), "hello");
+        assert!(list_files(&fs, "/.trash").is_empty());
+        // 回收站里再删一次才是彻底删除
+        fs.delete_file(&paths("/manga/oda/One Piece")).unwrap();
+        fs.delete_file(&paths("/.trash/One Piece")).unwrap();
+        assert!(labels(&fs, "One Piece").is_none());
+        // 超过保留时间的文件会被清理掉
+        let fs = fs.with_trash_retention(Duration::from_secs(0));
+        write(&fs, "/manga/oda/Naruto", "x").unwrap();
+        fs.delete_file(&paths("/manga/oda/Naruto")).unwrap();
+        assert_eq!(fs.purge_expired_trash().unwrap(), 1);
+        assert!(labels(&fs, "Naruto").is_none());
+    }
+
+    #[test]
+    fn test_props() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        let path = paths("/manga/oda/One Piece");
+        let props = fs.get_file_props(&path).unwrap();
+        let author = props.iter().find(|p| p.name == "author").unwrap();
+        assert_eq!(labelprop::to_values(author), Some(vec![String::from("oda")]));
+        let genre = labelprop::to_prop(
+            &String::from("genre"),
+            &vec![String::from("shonen"), String::from("adventure")],
+        )
+        .unwrap();
+        let result = fs.patch_file_props(&path, vec![(true, genre.clone())]).unwrap();
+        assert_eq!(result[0].0, StatusCode::OK);
+        assert_eq!(values(&fs, "One Piece", "genre"), vec!["adventure", "shonen"]);
+        // 有一个属性不能改时，其他的也都不生效
+        let title = labelprop::to_prop(&String::from(TITLE), &vec![String::from("x")]).unwrap();
+        let result = fs
+            .patch_file_props(&path, vec![(false, genre), (true, title)])
+            .unwrap();
+        let status: Vec<StatusCode> = result.iter().map(|(s, _)| *s).collect();
+        assert_eq!(status, vec![StatusCode::FAILED_DEPENDENCY, StatusCode::FORBIDDEN]);
+        assert_eq!(values(&fs, "One Piece", "genre"), vec!["adventure", "shonen"]);
+        // 标签目录下的标签文件没有属性
+        assert!(fs.get_file_props(&paths("/manga/oda/One Piece.labels")).is_err());
+    }
+
+    #[test]
+    fn test_label_files() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        // 文件本体不是目录，标签在旁边的标签目录里
+        assert!(list(&fs, "/manga/oda/One Piece").is_err());
+        assert_eq!(list(&fs, "/manga/oda/One Piece.labels").unwrap(), vec!["author=oda"]);
+        assert_eq!(read(&fs, "/manga/oda/One Piece.labels/author=oda").unwrap(), "oda");
+        write(&fs, "/manga/oda/One Piece.labels/genre", "shonen\nadventure\n").unwrap();
+        assert_eq!(values(&fs, "One Piece", "genre"), vec!["adventure", "shonen"]);
+        assert_eq!(read(&fs, "/manga/oda/One Piece.labels/genre").unwrap(), "shonen\nadventure");
+        let meta = fs.read_metadata(&paths("/manga/oda/One Piece.labels/genre")).unwrap();
+        assert_eq!(meta.len(), "shonen\nadventure".len() as u64);
+        fs.rename_file(
+            &paths("/manga/oda/One Piece.labels/genre=shonen"),
+            &paths("/manga/oda/One Piece.labels/genre=seinen"),
+        )
+        .unwrap();
+        fs.delete_file(&paths("/manga/oda/One Piece.labels/genre=adventure"))
+            .unwrap();
+        assert_eq!(values(&fs, "One Piece", "genre"), vec!["seinen"]);
+        // 由文件系统维护的标签不能写
+        let result = write(&fs, "/manga/oda/One Piece.labels/title", "x");
+        assert!(matches!(result, Err(FsError::Forbidden)));
+        assert!(read(&fs, "/manga/oda/One Piece.labels/genre=missing").is_err());
+        assert!(read(&fs, "/manga/oda/One Piece.labels/missing").is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        for dir in ["/", "/manga", "/manga/oda", "/.trash", "/manga/oda/One Piece.labels"] {
+            assert!(fs.read_metadata(&paths(dir)).unwrap().is_dir(), "{}", dir);
+        }
+        let meta = fs.read_metadata(&paths("/manga/oda/One Piece")).unwrap();
+        assert!(!meta.is_dir());
+        assert_eq!(meta.len(), 5);
+        let modified = values(&fs, "One Piece", MODIFIED_TIME);
+        assert_eq!(Some(meta.modified().unwrap()), parse_time(&modified[0]));
+        assert_ne!(meta.modified().unwrap(), UNIX_EPOCH);
+        let meta = fs
+            .read_metadata(&paths("/manga/oda/One Piece.labels/author"))
+            .unwrap();
+        assert!(!meta.is_dir());
+        assert_eq!(meta.len(), 3);
+        assert!(fs.read_metadata(&paths("/manga/oda/Naruto")).is_err());
+        assert!(fs.read_metadata(&paths("/unknown")).is_err());
+    }
+
+    #[test]
+    fn test_query_dir() {
+        let fs = manga_fs();
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        write(&fs, "/manga/toriyama/Dragon Ball", "kame").unwrap();
+        assert_eq!(list_files(&fs, "/_query/author:oda"), vec!["One Piece"]);
+        let mut names = list_files(&fs, "/_query/author:oda OR Dragon");
+        names.sort();
+        assert_eq!(names, vec!["Dragon Ball", "One Piece"]);
+        assert_eq!(read(&fs, "/_query/author:oda/One Piece").unwrap(), "hello");
+        assert!(read(&fs, "/_query/author:oda/Dragon Ball").is_err());
+        assert!(fs.read_metadata(&paths("/_query/author:oda")).unwrap().is_dir());
+        // 写错的查询当作目录不存在
+        assert!(list(&fs, "/_query/author:").is_err());
+    }
+
+    #[test]
+    fn test_facets() {
+        let fs = manga_fs();
+        define_collection(&fs, collection("facets", &["author", "genre"]));
+        write(&fs, "/facets/oda/shonen/One Piece", "x").unwrap();
+        write(&fs, "/facets/oda/x (1)/Odd", "x").unwrap();
+        write(&fs, "/facets/toriyama/shonen/Dragon Ball", "x").unwrap();
+        write(&fs, "/facets/toriyama/seinen/Sand Land", "x").unwrap();
+        let label = vec![
+            KV::new(String::from(TITLE), String::from("Jaco")),
+            KV::new(String::from("author"), String::from("toriyama")),
+        ];
+        fs.kv_file.add_file(&AddFileParams { label }).unwrap();
+        // 只列出已经选好的条件下还有文件的值，没有这个标签的文件在 _unset 下
+        assert_eq!(list(&fs, "/facets").unwrap(), vec!["oda", "toriyama"]);
+        assert_eq!(list(&fs, "/facets/oda").unwrap(), vec!["shonen", "x (1)"]);
+        assert_eq!(list(&fs, "/facets/toriyama").unwrap(), vec!["seinen", "shonen", UNSET_DIR]);
+        assert_eq!(list_files(&fs, "/facets/toriyama/_unset"), vec!["Jaco"]);
+        // _unset 是保留的名字，不能作为标签的值
+        let label = vec![
+            KV::new(String::from(TITLE), String::from("Bad")),
+            KV::new(String::from("genre"), String::from(UNSET_DIR)),
+        ];
+        assert!(fs.kv_file.add_file(&AddFileParams { label }).is_err());
+        assert!(write(&fs, "/facets/oda/shonen/One Piece.labels/genre", UNSET_DIR).is_err());
+        // 目录名带上数量，去掉数量或者带着数量都能访问
+        let fs = fs.with_value_counts(true);
+        assert_eq!(list(&fs, "/facets").unwrap(), vec!["oda (2)", "toriyama (3)"]);
+        assert_eq!(
+            list(&fs, "/facets/toriyama (3)").unwrap(),
+            vec!["seinen (1)", "shonen (1)", "_unset (1)"]
+        );
+        assert_eq!(list_files(&fs, "/facets/toriyama (3)/shonen (1)"), vec!["Dragon Ball"]);
+        assert_eq!(list_files(&fs, "/facets/toriyama/shonen"), vec!["Dragon Ball"]);
+        assert_eq!(list_files(&fs, "/facets/toriyama/_unset (1)"), vec!["Jaco"]);
+        // 值本身带括号时不会被当成数量去掉
+        assert_eq!(list(&fs, "/facets/oda").unwrap(), vec!["shonen (1)", "x (1) (1)"]);
+        assert_eq!(list_files(&fs, "/facets/oda/x (1)"), vec!["Odd"]);
+        assert_eq!(list_files(&fs, "/facets/oda/x (1) (1)"), vec!["Odd"]);
+    }
+
+    #[test]
+    fn test_duplicate_titles() {
+        let fs = manga_fs();
+        let mut ids = vec![];
+        for _ in 0..2 {
+            let label = vec![
+                KV::new(String::from(TITLE), String::from("Vol. 1")),
+                KV::new(String::from("author"), String::from("oda")),
+            ];
+            ids.push(fs.kv_file.add_file(&AddFileParams { label }).unwrap().id);
+        }
+        let names: Vec<String> = ids.iter().map(|id| format!("Vol. 1 (#{})", id)).collect();
+        assert_eq!(list_files(&fs, "/manga/oda"), names);
+        write(&fs, &format!("/manga/oda/{}", names[1]), "second").unwrap();
+        assert_eq!(read(&fs, &format!("/manga/oda/{}", names[1])).unwrap(), "second");
+        // 不带编号的名字对应多个文件
+        assert!(matches!(read(&fs, "/manga/oda/Vol. 1"), Err(FsError::Exists)));
+        // 同一个目录下不能移动出重名的文件
+        write(&fs, "/manga/toriyama/Vol. 1", "x").unwrap();
+        let result = fs.copy_file(&paths("/manga/toriyama/Vol. 1"), &paths("/manga/oda/Vol. 1"));
+        assert!(matches!(result, Err(FsError::Exists)));
+    }
+
+    #[test]
+    fn test_name_template() {
+        let fs = manga_fs();
+        let mut selector_set = collection("templated", &["author"]);
+        selector_set.name_template = Some(String::from("{{author}} - {{title}}"));
+        define_collection(&fs, selector_set);
+        write(&fs, "/manga/oda/One Piece", "hello").unwrap();
+        assert_eq!(list_files(&fs, "/templated/oda"), vec!["oda - One Piece"]);
+        assert_eq!(read(&fs, "/templated/oda/oda - One Piece").unwrap(), "hello");
+        // 渲染出来的名字没法反推出标题
+        let result = fs.rename_file(
+            &paths("/templated/oda/oda - One Piece"),
+            &paths("/templated/oda/oda - Two Piece"),
+        );
+        assert!(matches!(result, Err(FsError::Forbidden)));
+        assert!(matches!(write(&fs, "/templated/oda/New Name", "x"), Err(FsError::Forbidden)));
+        assert_eq!(list_files(&fs, "/manga/oda"), vec!["One Piece"]);
+    }
+
+    #[test]
+    fn test_sort() {
+        let fs = manga_fs();
+        for (title, volume) in [("Chapter 10", "2"), ("Chapter 2", "1"), ("Chapter 1", "1")] {
+            write(&fs, &format!("/manga/a10/{}", title), "x").unwrap();
+            write(&fs, &format!("/manga/a10/{}.labels/volume", title), volume).unwrap();
+        }
+        write(&fs, "/manga/a9/Other", "x").unwrap();
+        assert_eq!(list(&fs, "/manga").unwrap(), vec!["a9", "a10"]);
+        assert_eq!(list_files(&fs, "/manga/a10"), vec!["Chapter 1", "Chapter 2", "Chapter 10"]);
+        let mut selector_set = collection("by-volume", &["author"]);
+        selector_set.sort = vec![SortSpec::new("volume", true)];
+        define_collection(&fs, selector_set);
+        assert_eq!(list_files(&fs, "/by-volume/a10"), vec!["Chapter 10", "Chapter 1", "Chapter 2"]);
+    }
+
+    #[test]
+    fn test_collection_groups() {
+        let fs = manga_fs();
+        define_collection(&fs, collection("by-person/author", &["author"]));
+        define_collection(&fs, collection("by-person/genre", &["genre"]));
+        write(&fs, "/by-person/author/oda/One Piece", "hello").unwrap();
+        let root = list(&fs, "/").unwrap();
+        assert!(root.contains(&String::from("by-person")) && root.contains(&String::from(TRASH_DIR)));
+        assert_eq!(list(&fs, "/by-person").unwrap(), vec!["author", "genre"]);
+        assert_eq!(list(&fs, "/by-person/author").unwrap(), vec!["oda"]);
+        assert_eq!(read(&fs, "/by-person/author/oda/One Piece").unwrap(), "hello");
+        assert!(fs.read_metadata(&paths("/by-person")).unwrap().is_dir());
+        // 分组的名字不能再用作筛选器组
+        let result = fs.define_collection(&DefineCollectionParams {
+            selector_set: collection("by-person", &["author"]),
+        });
+        assert!(result.is_err());
+    }
+}
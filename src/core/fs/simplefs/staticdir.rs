@@ -7,7 +7,7 @@ use webdav_handler::fs::{DavDirEntry, DavFile, DavMetaData};
 use xml::name;
 use crate::core::fs::*;
 
-use crate::adapter::storage::{KVFile, SelectorSet, KV};
+use crate::adapter::storage::SelectorSet;
 
 #[derive(Debug, Clone)]
 pub struct StaticDir {
@@ -37,16 +37,6 @@ impl From<&String> for StaticDir {
     }
 }
 
-impl From<&KVFile> for StaticDir {
-    fn from(value: &KVFile) -> Self {
-        return StaticDir { 
-            name: KV::find_value_default(&value.label, &String::from(TITLE), String::from("untitiled")), 
-            // TODO: 使用真时间
-            modified_time: std::time::SystemTime::now(),
-        }
-    }
-}
-
 impl DavDirEntry for StaticDir {
     fn name(&self) -> Vec<u8> {
         self.name.to_string().into_bytes()
@@ -61,7 +51,7 @@ impl DavDirEntry for StaticDir {
 
 impl DavMetaData for StaticDir {
     fn len(&self) -> u64 {
-        12
+        0
     }
 
     fn modified(&self) -> webdav_handler::fs::FsResult<std::time::SystemTime> {
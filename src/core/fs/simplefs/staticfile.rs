@@ -41,6 +41,18 @@ impl DavDirEntry for StaticFile {
     }
 }
 
+impl StaticFile {
+    // 以 key 命名的标签文件，内容是这个 key 的所有值，每行一个
+    pub fn from_values(key: &String, values: &Vec<String>) -> Self {
+        let mut sb = StaticFileBuilder::create_empty();
+        sb.name(key.clone())
+            .modified_time(std::time::SystemTime::now())
+            .body(Bytes::from(values.join("\n")))
+            .offset(0);
+        return sb.build().unwrap();
+    }
+}
+
 impl From<&KV> for StaticFile {
     fn from(kv: &KV) -> Self {
         let mut name = String::new();
@@ -48,7 +60,10 @@ impl From<&KV> for StaticFile {
         name.push('=');
         name.push_str(&kv.value);
         let mut sb = StaticFileBuilder::create_empty();
-        sb.name(name);
+        sb.name(name)
+            .modified_time(std::time::SystemTime::now())
+            .body(Bytes::from(kv.value.clone()))
+            .offset(0);
         return sb.build().unwrap();
     }
 }
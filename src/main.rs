@@ -1,116 +1,35 @@
-use std::convert::Infallible;
 use std::error::Error;
 use std::io::Write;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use http::Response;
-use hyper::{self, body};
-use soapdav::adapter::storage::mem::MemSelectorSetStorage;
-use soapdav::adapter::storage::MemFileKVFileStorage;
-use soapdav::{AddFileParams, CollectionFS, DefineCollectionParams, DefineSelectorParams, RemoveCollectionParams, SimpleFileSystem};
+use soapdav::adapter::http::Server;
+use soapdav::adapter::storage::{LocalBlobStorage, LocalKVFileStorage, LocalSelectorSetStorage};
+use soapdav::SimpleFileSystem;
 
 use log::info;
-use webdav_handler::body::Body;
-use webdav_handler::{fakels, DavHandler};
 
-#[derive(Clone)]
-struct Server {
-    dh: DavHandler,
-    fs: SimpleFileSystem,
-}
-
-impl Server {
-    pub fn new() -> Self {
-        let selector_set_storage = Arc::new(MemSelectorSetStorage::new());
-        let new_kv = Arc::new(MemFileKVFileStorage::new());
-        let simplefs = SimpleFileSystem::new(selector_set_storage, new_kv.clone(), new_kv);
-        let config = DavHandler::builder()
-            .filesystem(Box::new(simplefs.clone()))
-            .locksystem(fakels::FakeLs::new())
-            .autoindex(true, None);
-        Server {
-            dh: config.build_handler(),
-            fs: simplefs,
-        }
-    }
-
-    async fn handle(
-        &self,
-        req: hyper::Request<hyper::Body>,
-    ) -> Result<hyper::Response<Body>, Infallible> {
-        match (req.method(), req.uri().path()) {
-            (_, "/manage/add_file") => return self.add_file(req).await,
-            (_, "/manage/define_collection") => return self.define_collection(req).await,
-            (_, "/manage/remove_collection") => return self.remove_collection(req).await,
-            (_, "/manage/define_selector") => return self.define_selector(req).await,
-            (method, path) => {
-                log::info!("receive dav request, method={}, path={}", method, path);
-                return Ok(self.dh.handle(req).await);
-            },
-        }
-    }
-
-    async fn add_file(
-        &self,
-        req: hyper::Request<hyper::Body>,
-    ) -> Result<hyper::Response<Body>, Infallible> {
-        let whole_body = body::to_bytes(req.into_body()).await.unwrap();
-        let str_body = std::str::from_utf8(&whole_body).unwrap();
-        let params: AddFileParams = serde_json::from_str(str_body).unwrap();
-        match self.fs.add_file(&params) {
-            Ok(r) => Ok(Response::new(Body::from(
-                serde_json::to_string(&r).unwrap(),
-            ))),
-            Err(_) => Ok(Response::new(Body::from(String::from("NotOk")))),
-        }
-    }
-
-    async fn define_collection(
-        &self,
-        req: hyper::Request<hyper::Body>,
-    ) -> Result<hyper::Response<Body>, Infallible> {
-        let whole_body = body::to_bytes(req.into_body()).await.unwrap();
-        let str_body = std::str::from_utf8(&whole_body).unwrap();
-        let params: DefineCollectionParams = serde_json::from_str(str_body).unwrap();
-        match self.fs.define_collection(&params) {
-            Ok(r) => Ok(Response::new(Body::from(
-                serde_json::to_string(&r).unwrap(),
-            ))),
-            Err(_) => Ok(Response::new(Body::from(String::from("NotOk")))),
-        }
-    }
-
-    async fn remove_collection(
-        &self,
-        req: hyper::Request<hyper::Body>,
-    ) -> Result<hyper::Response<Body>, Infallible> {
-        let whole_body = body::to_bytes(req.into_body()).await.unwrap();
-        let str_body = std::str::from_utf8(&whole_body).unwrap();
-        let params: RemoveCollectionParams = serde_json::from_str(str_body).unwrap();
-        match self.fs.remove_collection(&params) {
-            Ok(r) => Ok(Response::new(Body::from(
-                serde_json::to_string(&r).unwrap(),
-            ))),
-            Err(_) => Ok(Response::new(Body::from(String::from("NotOk")))),
-        }
-    }
+// 数据目录，可以通过环境变量 SOAPDAV_DATA_DIR 指定
+const DATA_DIR_ENV: &str = "SOAPDAV_DATA_DIR";
+const DEFAULT_DATA_DIR: &str = "data";
+// 回收站的保留天数，可以通过环境变量 SOAPDAV_TRASH_RETENTION_DAYS 指定
+const TRASH_RETENTION_DAYS_ENV: &str = "SOAPDAV_TRASH_RETENTION_DAYS";
+const DEFAULT_TRASH_RETENTION_DAYS: u64 = 30;
+const TRASH_CLEAN_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// 设置为 true 时筛选目录的名字后面带上文件数量
+const VALUE_COUNTS_ENV: &str = "SOAPDAV_VALUE_COUNTS";
 
-    async fn define_selector(
-        &self,
-        req: hyper::Request<hyper::Body>,
-    ) -> Result<hyper::Response<Body>, Infallible> {
-        let whole_body = body::to_bytes(req.into_body()).await.unwrap();
-        let str_body = std::str::from_utf8(&whole_body).unwrap();
-        let params: DefineSelectorParams = serde_json::from_str(str_body).unwrap();
-        match self.fs.define_selector(&params) {
-            Ok(r) => Ok(Response::new(Body::from(
-                serde_json::to_string(&r).unwrap(),
-            ))),
-            Err(_) => Ok(Response::new(Body::from(String::from("NotOk")))),
-        }
-    }
+fn open_filesystem(data_dir: &PathBuf, trash_retention: Duration) -> std::io::Result<SimpleFileSystem> {
+    let selector_set_storage = Arc::new(LocalSelectorSetStorage::open(data_dir)?);
+    let new_kv = Arc::new(LocalKVFileStorage::open(data_dir)?);
+    let blob = Arc::new(LocalBlobStorage::new(data_dir.join("blob"))?);
+    Ok(
+        SimpleFileSystem::new(selector_set_storage, new_kv.clone(), new_kv, blob)
+            .with_trash_retention(trash_retention),
+    )
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -129,7 +48,38 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .filter(None, log::LevelFilter::Debug)
         .init();
 
-    let dav_server = Server::new();
+    let data_dir = PathBuf::from(
+        std::env::var(DATA_DIR_ENV).unwrap_or_else(|_| String::from(DEFAULT_DATA_DIR)),
+    );
+    info!("data dir: {:?}", data_dir);
+    let trash_retention_days = match std::env::var(TRASH_RETENTION_DAYS_ENV) {
+        Ok(v) => v.parse::<u64>()?,
+        Err(_) => DEFAULT_TRASH_RETENTION_DAYS,
+    };
+    let value_counts = match std::env::var(VALUE_COUNTS_ENV) {
+        Ok(v) => v.parse::<bool>()?,
+        Err(_) => false,
+    };
+    let fs = open_filesystem(
+        &data_dir,
+        Duration::from_secs(trash_retention_days * 24 * 60 * 60),
+    )?
+    .with_value_counts(value_counts);
+    let dav_server = Server::new(fs.clone());
+
+    // 定期清理回收站中过期的文件
+    let trash_fs = fs;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(TRASH_CLEAN_INTERVAL);
+        loop {
+            interval.tick().await;
+            match trash_fs.purge_expired_trash() {
+                Ok(amount) => info!("purged {} expired files from trash", amount),
+                Err(e) => log::error!("failed to purge trash: {}", e),
+            }
+        }
+    });
+
     let make_service = hyper::service::make_service_fn(|_| {
         let dav_server = dav_server.clone();
         async move {
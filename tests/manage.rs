@@ -0,0 +1,179 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::{Body, Client, Method, Request, StatusCode};
+use serde_json::{json, Value};
+use soapdav::adapter::http::Server;
+use soapdav::adapter::storage::{LocalBlobStorage, MemFileKVFileStorage, MemSelectorSetStorage};
+use soapdav::SimpleFileSystem;
+
+// 使用内存存储启动一个服务，返回监听的地址
+async fn start_server() -> SocketAddr {
+    let kv = Arc::new(MemFileKVFileStorage::new());
+    let blob_dir = std::env::temp_dir().join(format!("soapdav-{}", uuid::Uuid::new_v4()));
+    let fs = SimpleFileSystem::new(
+        Arc::new(MemSelectorSetStorage::new()),
+        kv.clone(),
+        kv,
+        Arc::new(LocalBlobStorage::new(blob_dir).unwrap()),
+    );
+    let dav_server = Server::new(fs);
+    let make_service = hyper::service::make_service_fn(move |_| {
+        let dav_server = dav_server.clone();
+        async move {
+            let func = move |req| {
+                let dav_server = dav_server.clone();
+                async move { dav_server.handle(req).await }
+            };
+            Ok::<_, hyper::Error>(hyper::service::service_fn(func))
+        }
+    });
+    let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+async fn call(addr: SocketAddr, method: Method, path: &str, body: Option<String>) -> (StatusCode, Value) {
+    let req = Request::builder()
+        .method(method)
+        .uri(format!("http://{}{}", addr, path))
+        .body(body.map(Body::from).unwrap_or_else(Body::empty))
+        .unwrap();
+    let resp = Client::new().request(req).await.unwrap();
+    let status = resp.status();
+    let bytes = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_manage_api() {
+    let addr = start_server().await;
+
+    // 定义筛选项和筛选器组
+    let selector = json!({"key": "author", "default_value": "unknown", "set_default_for_history": false});
+    let (status, _) = call(addr, Method::POST, "/manage/v1/selectors", Some(selector.to_string())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let collection = json!({"selector_set": {
+        "name": "manga",
+        "static_selectors": [],
+        "dynamic_selectors": [{"key": "author", "value": []}],
+    }});
+    let (status, _) = call(addr, Method::POST, "/manage/v1/collections", Some(collection.to_string())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, body) = call(addr, Method::GET, "/manage/v1/collections", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["collections"].as_array().unwrap().len(), 1);
+
+    // 添加文件，重名的文件也可以添加，目录里用 (#id) 区分
+    let file = json!({"kvs": [{"key": "title", "value": "one piece"}, {"key": "author", "value": "oda"}]});
+    let (status, body) = call(addr, Method::POST, "/manage/v1/files", Some(file.to_string())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id = body["id"].as_u64().unwrap();
+    assert_eq!(body["label"]["author"], json!(["oda"]));
+    let (status, body) = call(addr, Method::POST, "/manage/v1/files", Some(file.to_string())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let id2 = body["id"].as_u64().unwrap();
+    assert_ne!(id2, id);
+    let (status, _) = call(addr, Method::DELETE, &format!("/manage/v1/files/{}", id2), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let ids = json!({"ids": [id2]});
+    let (status, body) = call(addr, Method::POST, "/manage/v1/trash/purge", Some(ids.to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["amount"], 1);
+    let (status, body) = call(addr, Method::POST, "/manage/v1/files", Some(String::from("{"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_argument");
+
+    // 按标签查找文件
+    let (status, body) = call(addr, Method::GET, "/manage/v1/files?author=oda", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let files = body["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["id"].as_u64(), Some(id));
+    let (status, _) = call(addr, Method::GET, &format!("/manage/v1/files/{}", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(addr, Method::GET, "/manage/v1/files/999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, body) = call(addr, Method::GET, "/manage/v1/search?q=author%3Aoda%20-piec", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["files"].as_array().unwrap().len(), 0);
+    let (status, body) = call(addr, Method::GET, "/manage/v1/search?q=author%3A%22oda%22%20piece", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["files"].as_array().unwrap().len(), 1);
+    let (status, body) = call(addr, Method::GET, "/manage/v1/search?q=author%3A", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_query");
+
+    // 修改标签
+    let label = json!({"label": {"author": ["oda", "toriyama"]}});
+    let path = format!("/manage/v1/files/{}/labels", id);
+    let (status, _) = call(addr, Method::PUT, &path, Some(label.to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = call(addr, Method::GET, "/manage/v1/selectors?key=author", None).await;
+    let values = body["selectors"][0]["value"].as_array().unwrap();
+    assert!(values.contains(&json!("toriyama")));
+    let label = json!({"label": {"body_size": ["1"]}});
+    let (status, _) = call(addr, Method::PUT, &path, Some(label.to_string())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 删除的文件进入回收站，可以还原
+    let (status, body) = call(addr, Method::DELETE, &format!("/manage/v1/files/{}", id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["amount"], 1);
+    let (_, body) = call(addr, Method::GET, "/manage/v1/files", None).await;
+    assert_eq!(body["files"].as_array().unwrap().len(), 0);
+    let (_, body) = call(addr, Method::GET, "/manage/v1/selectors?key=author", None).await;
+    let values = body["selectors"][0]["value"].as_array().unwrap();
+    assert!(!values.contains(&json!("toriyama")));
+    let ids = json!({"ids": [id]});
+    let (status, body) = call(addr, Method::POST, "/manage/v1/trash/restore", Some(ids.to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["amount"], 1);
+
+    // 未知的路由和方法
+    let (status, _) = call(addr, Method::PATCH, "/manage/v1/files", None).await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    let (status, _) = call(addr, Method::GET, "/manage/v1/unknown", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 修改筛选器组，引用的key必须存在
+    let update = json!({"new_name": "comic", "add_static_selectors": [{"key": "genre", "value": ["shonen"]}]});
+    let (status, _) = call(addr, Method::PATCH, "/manage/v1/collections/manga", Some(update.to_string())).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let update = json!({"new_name": "comic", "add_static_selectors": [{"key": "author", "value": ["oda"]}]});
+    let (status, body) = call(addr, Method::PATCH, "/manage/v1/collections/manga", Some(update.to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["collection"]["name"], "comic");
+
+    // 筛选器组还在使用的key只能级联改名
+    let rename = json!({"new_key": "writer"});
+    let (status, _) = call(addr, Method::PATCH, "/manage/v1/selectors/author", Some(rename.to_string())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let rename = json!({"new_key": "writer", "cascade": true});
+    let (status, body) = call(addr, Method::PATCH, "/manage/v1/selectors/author", Some(rename.to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["collections"], json!(["comic"]));
+    let (_, body) = call(addr, Method::GET, "/manage/v1/files?writer=oda", None).await;
+    assert_eq!(body["files"].as_array().unwrap().len(), 1);
+
+    // 删除筛选器组
+    let (status, _) = call(addr, Method::DELETE, "/manage/v1/collections/comic", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = call(addr, Method::GET, "/manage/v1/collections", None).await;
+    assert_eq!(body["collections"].as_array().unwrap().len(), 0);
+
+    // 名字带有 '/' 的筛选器组放在分组目录里，分组本身不能再是筛选器组
+    let collection = json!({"selector_set": {
+        "name": "by-person/writer",
+        "static_selectors": [],
+        "dynamic_selectors": [{"key": "writer", "value": []}],
+    }});
+    let (status, _) = call(addr, Method::POST, "/manage/v1/collections", Some(collection.to_string())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let collection = json!({"selector_set": {"name": "by-person", "static_selectors": [], "dynamic_selectors": []}});
+    let (status, _) = call(addr, Method::POST, "/manage/v1/collections", Some(collection.to_string())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = call(addr, Method::DELETE, "/manage/v1/collections/by-person%2Fwriter", None).await;
+    assert_eq!(status, StatusCode::OK);
+}